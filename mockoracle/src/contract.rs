@@ -0,0 +1,82 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128
+};
+use cw2::set_contract_version;
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::state::{Config, CONFIG};
+use stknoracle::{OracleQueryMsg, OraclePriceResponse};
+
+// Version info, for migration info
+const CONTRACT_NAME: &str = "mockoracle";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg
+) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    CONFIG.save(deps.storage, &Config {
+        owner: info.sender.clone(),
+        price: msg.price,
+        last_updated: env.block.time.seconds()
+    })?;
+
+    Ok(Response::new().add_attribute("action", "instantiate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::SetPrice { price, last_updated } => execute_set_price(deps, env, info, price, last_updated),
+    }
+}
+
+pub fn execute_set_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    price: Uint128,
+    last_updated: Option<u64>
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    if cfg.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    cfg.price = price;
+    cfg.last_updated = last_updated.unwrap_or_else(|| env.block.time.seconds());
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_price")
+        .add_attribute("price", price)
+        .add_attribute("last_updated", cfg.last_updated.to_string())
+    )
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: OracleQueryMsg) -> StdResult<Binary> {
+    match msg {
+        OracleQueryMsg::Price {} => to_binary(&query_price(deps)?),
+    }
+}
+
+pub fn query_price(deps: Deps) -> StdResult<OraclePriceResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    Ok(OraclePriceResponse {
+        price: cfg.price,
+        last_updated: cfg.last_updated
+    })
+}
@@ -5,7 +5,7 @@ use cosmwasm_std::{
 };
 use cw0::parse_reply_instantiate_data;
 use cw2::set_contract_version;
-use cw_storage_plus::Bound;
+use cw_storage_plus::{Bound, Item};
 use cw20::Denom::{Cw20};
 use cw20::{Cw20ExecuteMsg, Denom, Expiration, MinterResponse};
 use cw20_base::contract::query_balance;
@@ -25,6 +25,9 @@ pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 
 const INSTANTIATE_LP_TOKEN_REPLY_ID: u64 = 0;
+
+/// Set by the manager when the stock is delisted, only liquidity removal is accepted afterwards
+const WITHDRAW_ONLY: Item<bool> = Item::new("withdraw_only");
 // Note, you can use StdResult in some functions where you do not
 // make use of the custom errors
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -183,7 +186,58 @@ pub fn execute(
             expiration,
         ),
         ExecuteMsg::SyncPrice { } => execute_sync_price(deps, env, info),
+        ExecuteMsg::Split { numerator, denominator } => execute_split(deps, info, numerator, denominator),
+        ExecuteMsg::SetWithdrawOnly {} => execute_set_withdraw_only(deps, info),
+    }
+}
+
+pub fn execute_set_withdraw_only(
+    deps: DepsMut,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let stock_response = util::get_stock_config(deps.querier, cfg.stock_address.clone())?;
+    if info.sender != stock_response.manager_address {
+        return Err(ContractError::Unauthorized {});
+    }
+    WITHDRAW_ONLY.save(deps.storage, &true)?;
+
+    Ok(Response::new().add_attribute("action", "set_withdraw_only"))
+}
+
+fn check_not_withdraw_only(deps: Deps) -> Result<(), ContractError> {
+    if WITHDRAW_ONLY.may_load(deps.storage)?.unwrap_or(false) {
+        return Err(StdError::generic_err("Pool only accepts withdrawals").into());
     }
+    Ok(())
+}
+
+/// Stock token balances are rebased by the split, the stock reserve follows them
+pub fn execute_split(
+    deps: DepsMut,
+    info: MessageInfo,
+    numerator: u64,
+    denominator: u64
+) -> Result<Response, ContractError> {
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let stock_response = util::get_stock_config(deps.querier, cfg.stock_address.clone())?;
+    if info.sender != stock_response.manager_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let token1 = TOKEN1.update(deps.storage, |mut token1| -> StdResult<_> {
+        token1.reserve = token1.reserve.multiply_ratio(numerator, denominator);
+        Ok(token1)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "split")
+        .add_attribute("numerator", numerator.to_string())
+        .add_attribute("denominator", denominator.to_string())
+        .add_attribute("token1_reserve", token1.reserve)
+    )
 }
 
 pub fn execute_sync_price(
@@ -193,14 +247,17 @@ pub fn execute_sync_price(
 ) -> Result<Response, ContractError> {
     
     let cfg = CONFIG.load(deps.storage)?;
-    util::check_stock_owner(deps.querier, cfg.stock_address.clone(), info.sender.clone())?;
+    let stock_response = util::get_stock_config(deps.querier, cfg.stock_address.clone())?;
+    // the manager syncs due pools for keepers
+    if info.sender != stock_response.manager_address {
+        util::check_stock_owner(deps.querier, cfg.stock_address.clone(), info.sender.clone())?;
+    }
     util::check_stock_enabled(deps.querier, cfg.stock_address.clone())?;
     
     //Stock price displaying with PUSD
     let stock = TOKEN1.load(deps.storage)?.reserve;
     let pusd = TOKEN2.load(deps.storage)?.reserve;
 
-    let stock_response = util::get_stock_config(deps.querier, cfg.stock_address.clone())?;
     let new_pusd = stock.checked_mul(stock_response.price).unwrap().checked_div(Uint128::from(NORMAL_DECIMAL)).unwrap();
     
     TOKEN2.update(deps.storage, |mut exists| -> StdResult<_> {
@@ -222,16 +279,26 @@ pub fn execute_sync_price(
                 amount: new_pusd - pusd
             })?,
         }));
-    } else {
-        //PUSD token is too much, so we have to burn PUSD
+    } else if new_pusd < pusd {
+        //PUSD token is too much, so the manager burns it and lowers the minted amount of the stock
         messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: manager_response.pusd_address.clone().into(),
             funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Burn {
-                amount: pusd - new_pusd
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: stock_response.manager_address.clone().into(),
+                amount: pusd - new_pusd,
+                msg: to_binary(&util::GlobalReceiveMsg::Burn {
+                    id: stock_response.id
+                })?
             })?,
         }));
     }
+    // the manager tracks the last sync of every pool, also when the owner syncs directly
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: stock_response.manager_address.clone().into(),
+        funds: vec![],
+        msg: to_binary(&util::GlobalExecuteMsg::RecordSync {})?,
+    }));
 
     Ok(Response::new()
         .add_attribute("action", "sync_price")
@@ -304,6 +371,7 @@ pub fn execute_add_liquidity(
     let cfg = CONFIG.load(deps.storage)?;
     util::check_stock_enabled(deps.querier, cfg.stock_address.clone())?;
     util::check_stock_subcontract(deps.querier, cfg.stock_address.clone(), info.sender.clone())?;
+    check_not_withdraw_only(deps.as_ref())?;
 
     check_expiration(&expiration, &env.block)?;
 
@@ -490,9 +558,11 @@ fn get_cw20_increase_allowance_msg(
     Ok(exec_allowance.into())
 }
 
-pub const TREASURY_ADDR1: &str = "juno19380pt5d828stn7d9u4w53rz0zf55cl9xcfue0";
-pub const TREASURY_ADDR2: &str = "juno1z0fdt2j7kv7ykl8mvxzgyrehlvj3cxat6jfwwy";
-pub const TREASURY_ADDR3: &str = "juno1eatqlgaenthn4c6g50vnhmcnd0fh72sz08kx86";
+/// Only the manager itself acts as treasury, e.g. executing a passed multisig proposal
+fn is_treasury(deps: Deps, stock_address: Addr, address: Addr) -> bool {
+    util::get_stock_config(deps.querier, stock_address)
+        .map_or(false, |stock_response| stock_response.manager_address == address)
+}
 
 pub fn execute_remove_liquidity(
     deps: DepsMut,
@@ -509,6 +579,7 @@ pub fn execute_remove_liquidity(
     util::check_stock_subcontract(deps.querier, cfg.stock_address.clone(), info.sender.clone())?;
 
     check_expiration(&expiration, &env.block)?;
+    let treasury = is_treasury(deps.as_ref(), cfg.stock_address.clone(), info.sender.clone());
 
     let lp_token_addr = LP_TOKEN.load(deps.storage)?;
     let balance = get_token_balance(deps.as_ref(), &lp_token_addr, &info.sender)?;
@@ -516,7 +587,7 @@ pub fn execute_remove_liquidity(
     let token1 = TOKEN1.load(deps.storage)?;
     let token2 = TOKEN2.load(deps.storage)?;
 
-    if info.sender.clone() != deps.api.addr_validate(TREASURY_ADDR1)? && info.sender.clone() != deps.api.addr_validate(TREASURY_ADDR2)? && info.sender.clone() != deps.api.addr_validate(TREASURY_ADDR3)? {
+    if !treasury {
         if amount > balance {
             return Err(ContractError::InsufficientLiquidityError {
                 requested: amount,
@@ -578,7 +649,7 @@ pub fn execute_remove_liquidity(
     messages.push(token1_transfer_msg);
     messages.push(token2_transfer_msg);
 
-    if info.sender.clone() != deps.api.addr_validate(TREASURY_ADDR1)? && info.sender.clone() != deps.api.addr_validate(TREASURY_ADDR2)? && info.sender.clone() != deps.api.addr_validate(TREASURY_ADDR3)? {
+    if !treasury {
         let lp_token_burn_msg = get_burn_msg(&lp_token_addr, &info.sender, amount)?;
         messages.push(lp_token_burn_msg);
     }
@@ -711,6 +782,7 @@ pub fn execute_swap(
     let cfg = CONFIG.load(deps.storage)?;
     util::check_stock_enabled(deps.querier, cfg.stock_address.clone())?;
     // util::check_stock_subcontract(deps.querier, cfg.stock_address.clone(), info.sender.clone())?;
+    check_not_withdraw_only(deps.as_ref())?;
 
     check_expiration(&expiration, &_env.block)?;
 
@@ -799,6 +871,7 @@ pub fn execute_pass_through_swap(
     let cfg = CONFIG.load(deps.storage)?;
     util::check_stock_enabled(deps.querier, cfg.stock_address.clone())?;
     // util::check_stock_subcontract(deps.querier, cfg.stock_address.clone(), info.sender.clone())?;
+    check_not_withdraw_only(deps.as_ref())?;
 
     check_expiration(&expiration, &_env.block)?;
 
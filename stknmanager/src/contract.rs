@@ -1,26 +1,36 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, to_binary, from_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
-    WasmMsg, WasmQuery, QueryRequest, CosmosMsg, Order, Addr, SubMsg, ReplyOn, Reply, Storage
+    attr, to_binary, from_binary, Binary, Event, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+    WasmMsg, WasmQuery, QueryRequest, CosmosMsg, Order, Addr, SubMsg, ReplyOn, Reply, Storage, StdError, Uint256, Decimal, ContractInfoResponse
 };
-use cw_utils::parse_reply_instantiate_data;
+use std::convert::TryFrom;
+use cw_storage_plus::Bound;
+use cw_utils::{ensure_from_older_version, parse_reply_instantiate_data};
 use cw2::{get_contract_version, set_contract_version};
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse, Logo, Denom};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse, MarketingInfoResponse, Logo, Denom, Expiration, TokenInfoResponse};
 use cw20_base::msg::{InstantiateMsg as Cw20InstantiateMsg, InstantiateMarketingInfo};
 use crate::error::ContractError;
 use crate::msg::{
-    ExecuteMsg, InstantiateMsg, MigrateMsg, ReceiveMsg
+    ExecuteMsg, InstantiateMsg, MarketingToken, MigrateMsg, ReceiveMsg
 };
 
 use crate::state::{
-    Config, CONFIG, STOCKS
+    Config, CONFIG, Bootstrap, BootstrapStage, BOOTSTRAP, next_bootstrap_stage, PendingStock, PENDING_STOCKS, Subcontract, ACCRUED_FEES, Settlement, SETTLEMENT, SettledStock, SETTLED_STOCKS, STKN_FUNDERS, ReserveRecord, RESERVE_HISTORY, RESERVE_HISTORY_COUNT, STOCKS, MintKind, MintLimit, MintUsage, MINT_LIMITS, MINT_USAGE, CALLER_MINTED, STOCK_COUNT, STOCK_SYMBOLS, symbol_key, is_valid_symbol, PendingOwner, PENDING_OWNER, Role, ROLES,
+    CorporateAction, CorporateActionRecord, CORPORATE_ACTIONS, CORPORATE_ACTION_COUNT,
+    SyncRecord, LAST_SYNCS, SYNC_CURSOR, KeeperRun, KEEPER_RUN, CircuitBreaker, CIRCUIT_BREAKERS, PriceWindow, BREAKER_WINDOWS, BreakerTrip, BREAKER_TRIPS,
+    MultisigConfig, MULTISIG_CONFIG, MULTISIG_MEMBERS, Proposal, ProposalStatus, PROPOSALS, PROPOSAL_COUNT, PROPOSAL_VOTES,
+    PUSD_DECIMALS, AdminAuth, TimelockCategory, TIMELOCK_DELAYS, ScheduledOperation, SCHEDULED_OPERATIONS, SCHEDULED_OPERATION_COUNT,
+    AddressRole, ContractRole, ADDRESS_ROLES, Delisting, DELISTINGS, ArchivedStock, ARCHIVED_STOCKS, register_protocol_addresses, register_stock_addresses, unregister_stock_addresses
 };
 use cw20_stock::msg::{InstantiateMsg as StockInstantiateMsg, InstantiateMarketingInfo as StockInstantiateMarketingInfo};
-use stknstaking::msg::{InstantiateMsg as StakingInstantiateMsg};
+use stknstaking::msg::{InstantiateMsg as StakingInstantiateMsg, ExecuteMsg as StakingExecuteMsg};
 
+use crate::migration;
 use crate::util;
-use crate::util::{ManagerConfigResponse, ManagerQueryMsg, StockListResponse, StockInfo, StockConfigResponse, StockQueryMsg, NORMAL_DECIMAL};
+use crate::util::{ManagerConfigResponse, OpenPositionsResponse, StockConfigResponse, StockQueryMsg, SubcontractQueryMsg, NORMAL_DECIMAL};
+use crate::manager;
+use crate::manager::{BootstrapStatusResponse, CorporateActionsResponse, StockMetadata, CircuitBreakerResponse, DueStock, DueForSyncResponse, PoolExecuteMsg, MultisigResponse, ProposalListResponse, ProposalVoteResponse, TimelockDelaysResponse, ScheduledOperationsResponse, DelistingResponse, ArchivedStockListResponse, SubcontractExecuteMsg, SubcontractRoleResponse, PriceResponse, StockCodeIdsResponse, SubcontractCodeInfo, SettlementResponse, SolvencyResponse, StockMintedResponse, ReserveHistoryResponse, MintUsageResponse, SimulateSwapResponse, ManagerQueryMsg, StockListResponse, StockCountResponse, StockInfo, PendingOwnerResponse, RoleMembersResponse, StockStatus, StockStatusResponse};
 // Version info, for migration info
 const CONTRACT_NAME: &str = "stknmanager";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -29,7 +39,21 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const INSTANTIATE_PUSD_ID: u64 = 1;
 const INSTANTIATE_STAKING_ID: u64 = 2;
-const INSTANTIATE_STOCK_ID: u64 = 3;
+// SyncDue replies use SYNC_REPLY_ID + stock id, above every AddStock reply id
+const SYNC_REPLY_ID: u64 = 1 << 32;
+// AddStock replies use INSTANTIATE_STOCK_ID + stock id, so several stocks can be added in one block
+const INSTANTIATE_STOCK_ID: u64 = 1000;
+
+const FEE_DENOMINATOR: u64 = 10000;
+const MAX_FEE_BPS: u64 = 1000;
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+// entries a filtered listing reads per call
+const MAX_SCAN: usize = 300;
+// Keepers cannot sync a pool more often than this, whatever providing_sync_interval says
+const MIN_SYNC_INTERVAL: u64 = 60;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -40,11 +64,38 @@ pub fn instantiate(
 ) -> StdResult<Response> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    if msg.buy_pusd_fee_bps > MAX_FEE_BPS || msg.sell_pusd_fee_bps > MAX_FEE_BPS {
+        return Err(StdError::generic_err(ContractError::InvalidFee { max_bps: MAX_FEE_BPS }.to_string()));
+    }
+    let fee_collector = match msg.fee_collector.clone() {
+        Some(fee_collector) => deps.api.addr_validate(fee_collector.as_str())?,
+        None => info.sender.clone()
+    };
+
+    if msg.staking.lock_days.is_empty() || msg.staking.lock_days.len() != msg.staking.ratios.len() {
+        return Err(StdError::generic_err(ContractError::InvalidInput {}.to_string()));
+    }
+    if msg.providing_sync_interval < MIN_SYNC_INTERVAL {
+        return Err(StdError::generic_err(ContractError::SyncIntervalTooShort { min: MIN_SYNC_INTERVAL }.to_string()));
+    }
+    let pusd_address = match msg.pusd_address.clone() {
+        Some(pusd_address) => {
+            let pusd_address = deps.api.addr_validate(pusd_address.as_str())?;
+            check_adopted_pusd(deps.as_ref(), &env, &pusd_address)?;
+            pusd_address
+        },
+        None => msg.stkn_address.clone()
+    };
+    let staking_address = match msg.staking_address.clone() {
+        Some(staking_address) => deps.api.addr_validate(staking_address.as_str())?,
+        None => msg.stkn_address.clone()
+    };
+
     let config = Config {
-        owner: info.sender.clone(),
+        owner: Some(info.sender.clone()),
         stkn_address: msg.stkn_address.clone(),
-        pusd_address: msg.stkn_address.clone(),
-        staking_address: msg.stkn_address.clone(),
+        pusd_address,
+        staking_address,
 
         staking_code_id: msg.staking_code_id,
         cw20_code_id: msg.cw20_code_id,
@@ -56,120 +107,268 @@ pub fn instantiate(
         providing_code_id: msg.providing_code_id,
 
         price: msg.price,
+        price_updated_at: env.block.time.seconds(),
+        oracle_address: msg.oracle_address.clone(),
+        max_price_age: msg.max_price_age,
+        buy_pusd_fee_bps: msg.buy_pusd_fee_bps,
+        sell_pusd_fee_bps: msg.sell_pusd_fee_bps,
+        fee_collector,
+        min_stkn_reserve: Uint128::zero(),
+        min_collateral_ratio: None,
         max_stock_id: 0u32,
         enabled: true,
 
-        providing_sync_interval: msg.providing_sync_interval
+        providing_sync_interval: msg.providing_sync_interval,
+        keeper_reward: Uint128::zero()
     };
     CONFIG.save(deps.storage, &config)?;
+    register_protocol_addresses(deps.storage, &config)?;
+    STOCK_COUNT.save(deps.storage, &0u32)?;
+    ACCRUED_FEES.save(deps.storage, &Uint128::zero())?;
+    RESERVE_HISTORY_COUNT.save(deps.storage, &0u64)?;
+    SCHEDULED_OPERATION_COUNT.save(deps.storage, &0u64)?;
+    PROPOSAL_COUNT.save(deps.storage, &0u64)?;
+    CORPORATE_ACTION_COUNT.save(deps.storage, &0u64)?;
 
-    let sub_msg: Vec<SubMsg> = vec![SubMsg {
-        msg: WasmMsg::Instantiate {
-            code_id: msg.cw20_code_id,
-            funds: vec![],
-            admin: Some(info.sender.clone().into()),
-            label: String::from("PUSD : stable coin of Stocken"),
-            msg: to_binary(&Cw20InstantiateMsg {
-                name: String::from("PUSD"),
-                symbol: String::from("PUSD"),
-                decimals: 6,
-                initial_balances: vec![],
-                mint: Some(MinterResponse {
-                    minter: env.contract.address.clone().into(),
-                    cap: None
-                }),
-                marketing: Some(InstantiateMarketingInfo {
-                    project: None,
-                    description: None,
-                    logo: Some(Logo::Url(msg.pusd_url)),
-                    marketing: Some(env.contract.address.clone().into())
-                })
-            })?,
-        }.into(),
-        id: INSTANTIATE_PUSD_ID,
-        gas_limit: None,
-        reply_on: ReplyOn::Success,
-    }];
+    let bootstrap = Bootstrap {
+        stage: next_bootstrap_stage(&config),
+        last_error: None,
+        pusd: msg.pusd,
+        staking: msg.staking
+    };
+    BOOTSTRAP.save(deps.storage, &bootstrap)?;
 
-    Ok(Response::new().add_submessages(sub_msg))
+    let mut response = Response::new()
+        .add_attribute("action", "instantiate")
+        .add_attribute("bootstrap_stage", bootstrap.stage.as_str());
+    if bootstrap.stage != BootstrapStage::Done {
+        response = response.add_submessage(bootstrap_sub_msg(&env, &config, &bootstrap)?);
+    }
+    Ok(response)
 }
 
+/// An adopted PUSD has to be mintable by the manager and use the decimals of a bootstrapped one
+fn check_adopted_pusd(deps: Deps, env: &Env, pusd_address: &Addr) -> StdResult<()> {
+    let minter: Option<MinterResponse> = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: pusd_address.clone().into(),
+        msg: to_binary(&Cw20QueryMsg::Minter {})?,
+    }))?;
+    if minter.map_or(true, |minter| minter.minter != env.contract.address.as_str()) {
+        return Err(StdError::generic_err(ContractError::PusdMinterMismatch {}.to_string()));
+    }
+    let token_info: TokenInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: pusd_address.clone().into(),
+        msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+    }))?;
+    if token_info.decimals != PUSD_DECIMALS {
+        return Err(StdError::generic_err(ContractError::InvalidPusdDecimals {
+            expected: PUSD_DECIMALS,
+            decimals: token_info.decimals
+        }.to_string()));
+    }
+    Ok(())
+}
 
-// Reply callback triggered from cw20-base contract instantiation
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
-    
-    let mut cfg: Config = CONFIG.load(deps.storage)?;
-    let reply = parse_reply_instantiate_data(msg.clone()).unwrap();
-    let contract_address = Addr::unchecked(reply.contract_address);
-
-    if msg.id == INSTANTIATE_PUSD_ID {
-        cfg.pusd_address = contract_address.clone();
-        CONFIG.save(deps.storage, &cfg)?;
-
-        //Instantiate Staking
-        let sub_msg: Vec<SubMsg> = vec![SubMsg {
+/// Instantiate message for the current bootstrap stage
+fn bootstrap_sub_msg(
+    env: &Env,
+    cfg: &Config,
+    bootstrap: &Bootstrap
+) -> StdResult<SubMsg> {
+    let owner = cfg.owner.clone()
+        .ok_or_else(|| StdError::generic_err(ContractError::OwnershipRenounced {}.to_string()))?;
+    match bootstrap.stage {
+        BootstrapStage::Pusd => Ok(SubMsg {
+            msg: WasmMsg::Instantiate {
+                code_id: cfg.cw20_code_id,
+                funds: vec![],
+                admin: Some(owner.into()),
+                label: bootstrap.pusd.label.clone(),
+                msg: to_binary(&Cw20InstantiateMsg {
+                    name: bootstrap.pusd.name.clone(),
+                    symbol: bootstrap.pusd.symbol.clone(),
+                    decimals: PUSD_DECIMALS,
+                    initial_balances: vec![],
+                    mint: Some(MinterResponse {
+                        minter: env.contract.address.clone().into(),
+                        cap: bootstrap.pusd.cap
+                    }),
+                    marketing: Some(InstantiateMarketingInfo {
+                        project: None,
+                        description: None,
+                        logo: Some(Logo::Url(bootstrap.pusd.url.clone())),
+                        marketing: Some(env.contract.address.clone().into())
+                    })
+                })?,
+            }.into(),
+            id: INSTANTIATE_PUSD_ID,
+            gas_limit: None,
+            reply_on: ReplyOn::Always,
+        }),
+        BootstrapStage::Staking => Ok(SubMsg {
             msg: WasmMsg::Instantiate {
                 code_id: cfg.staking_code_id,
                 funds: vec![],
-                admin: Some(cfg.owner.clone().into()),
-                label: String::from("STKN Staking"),
+                admin: Some(owner.clone().into()),
+                label: bootstrap.staking.label.clone(),
                 msg: to_binary(&StakingInstantiateMsg {
-                    owner: cfg.owner.clone().into(),
+                    owner: owner.into(),
                     manager_address: env.contract.address.clone(),
-                    lock_days: vec![30, 60, 120],
-                    ratios: vec![20000, 40000, 80000],
+                    lock_days: bootstrap.staking.lock_days.clone(),
+                    ratios: bootstrap.staking.ratios.clone(),
                 })?,
             }.into(),
             id: INSTANTIATE_STAKING_ID,
             gas_limit: None,
-            reply_on: ReplyOn::Success,
-        }];
+            reply_on: ReplyOn::Always,
+        }),
+        BootstrapStage::Done => Err(StdError::generic_err(ContractError::BootstrapDone {}.to_string())),
+    }
+}
+
+pub fn check_bootstrapped(storage: &dyn Storage) -> Result<bool, ContractError> {
+    if BOOTSTRAP.load(storage)?.stage != BootstrapStage::Done {
+        return Err(ContractError::BootstrapNotDone {});
+    }
+    Ok(true)
+}
+
+// Reply callback triggered from cw20-base contract instantiation
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        INSTANTIATE_PUSD_ID | INSTANTIATE_STAKING_ID => reply_bootstrap(deps, env, msg),
+        id if id >= SYNC_REPLY_ID => reply_sync(msg),
+        id if id > INSTANTIATE_STOCK_ID => reply_add_stock(deps, env, msg),
+        _ => Err(ContractError::InvalidTokenReplyId {}),
+    }
+}
+
+/// Only failed syncs reply, the pool is skipped and stays due
+fn reply_sync(msg: Reply) -> Result<Response, ContractError> {
+    let id = msg.id - SYNC_REPLY_ID;
+    let error = msg.result.into_result().err().unwrap_or_default();
+    Ok(Response::new()
+        .add_attribute("action", "sync_failed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("error", error)
+    )
+}
+
+/// Bootstrap replies never fail, a failed stage is recorded and can be retried with ResumeBootstrap.
+fn reply_bootstrap(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let mut cfg: Config = CONFIG.load(deps.storage)?;
+    let mut bootstrap = BOOTSTRAP.load(deps.storage)?;
+
+    let expected_id = match bootstrap.stage {
+        BootstrapStage::Pusd => INSTANTIATE_PUSD_ID,
+        BootstrapStage::Staking => INSTANTIATE_STAKING_ID,
+        BootstrapStage::Done => return Err(ContractError::BootstrapDone {}),
+    };
+    if msg.id != expected_id {
+        return Err(ContractError::InvalidTokenReplyId {});
+    }
+
+    let contract_address = match parse_reply_instantiate_data(msg.clone()) {
+        Ok(reply) => deps.api.addr_validate(&reply.contract_address)?,
+        Err(err) => {
+            bootstrap.last_error = Some(err.to_string());
+            BOOTSTRAP.save(deps.storage, &bootstrap)?;
+            return Ok(Response::new()
+                .add_attribute("action", "bootstrap_failed")
+                .add_attribute("stage", bootstrap.stage.as_str())
+                .add_attribute("error", err.to_string())
+            );
+        }
+    };
+    bootstrap.last_error = None;
+
+    if msg.id == INSTANTIATE_PUSD_ID {
+        cfg.pusd_address = contract_address;
+        CONFIG.save(deps.storage, &cfg)?;
+        register_protocol_addresses(deps.storage, &cfg)?;
+        bootstrap.stage = next_bootstrap_stage(&cfg);
+        BOOTSTRAP.save(deps.storage, &bootstrap)?;
 
-        return Ok(Response::new()
-            .add_submessages(sub_msg)
+        let mut response = Response::new()
             .add_attribute("action", "instantiate_pusd")
-            .add_attribute("pusd_address", cfg.pusd_address.clone())
-        );
-    } else if msg.id == INSTANTIATE_STAKING_ID {
-        
-        cfg.staking_address = contract_address.clone();
+            .add_attribute("pusd_address", cfg.pusd_address.clone());
+        // Instantiate Staking unless an existing one was adopted
+        if bootstrap.stage != BootstrapStage::Done {
+            response = response.add_submessage(bootstrap_sub_msg(&env, &cfg, &bootstrap)?);
+        }
+        Ok(response)
+    } else {
+        cfg.staking_address = contract_address;
         CONFIG.save(deps.storage, &cfg)?;
+        register_protocol_addresses(deps.storage, &cfg)?;
+        bootstrap.stage = next_bootstrap_stage(&cfg);
+        BOOTSTRAP.save(deps.storage, &bootstrap)?;
 
-        return Ok(Response::new()
+        Ok(Response::new()
             .add_attribute("action", "instantiate_staking")
             .add_attribute("staking_address", cfg.staking_address.clone())
-        );
-    }else if msg.id == INSTANTIATE_STOCK_ID {
-        cfg.max_stock_id += 1;
-        CONFIG.save(deps.storage, &cfg)?;
+        )
+    }
+}
 
-        let stock_address = contract_address.clone();
+fn reply_add_stock(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let pending = PENDING_STOCKS.load(deps.storage, msg.id)?;
+    PENDING_STOCKS.remove(deps.storage, msg.id);
 
-        let stock_response: StockConfigResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: stock_address.clone().into(),
-            msg: to_binary(&StockQueryMsg::Config {})?,
-        }))?;
+    let reply = parse_reply_instantiate_data(msg)?;
+    let stock_address = deps.api.addr_validate(&reply.contract_address)?;
 
-        STOCKS.save(deps.storage, cfg.max_stock_id, 
-            &StockInfo {
-                id: cfg.max_stock_id,
-                stock_address: stock_address.clone(),
-                pool_address: stock_response.pool_address.clone(),
-                shorting_address: stock_response.shorting_address.clone(),
-                trading_address: stock_response.trading_address.clone(),
-                providing_address: stock_response.providing_address.clone()
-            }
-        )?;
+    let stock_response: StockConfigResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: stock_address.clone().into(),
+        msg: to_binary(&StockQueryMsg::Config {})?,
+    }))?;
 
-        return Ok(Response::new()
-            .add_attribute("action", "instantiate_stock")
-            .add_attribute("stock_address", stock_address.clone())
-        );
-    } else {
-        return Err(ContractError::InvalidTokenReplyId {});
+    let stock_info = StockInfo {
+        id: pending.id,
+        symbol: pending.symbol,
+        stock_address: stock_address.clone(),
+        pool_address: stock_response.pool_address.clone(),
+        shorting_address: stock_response.shorting_address.clone(),
+        trading_address: stock_response.trading_address.clone(),
+        providing_address: stock_response.providing_address.clone(),
+        status: StockStatus::Active,
+        metadata: pending.metadata
+    };
+    STOCKS.save(deps.storage, pending.id, &stock_info)?;
+    register_stock_addresses(deps.storage, &stock_info)?;
+    STOCK_COUNT.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "instantiate_stock")
+        .add_attribute("id", pending.id.to_string())
+        .add_attribute("stock_address", stock_address)
+    )
+}
+
+pub fn execute_resume_bootstrap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let bootstrap = BOOTSTRAP.load(deps.storage)?;
+    if bootstrap.stage == BootstrapStage::Done {
+        return Err(ContractError::BootstrapDone {});
     }
-    
+    // managers migrated from 1.0 have no url until one is passed to migrate
+    if bootstrap.stage == BootstrapStage::Pusd && bootstrap.pusd.url.is_empty() {
+        return Err(ContractError::MissingPusdUrl {});
+    }
+
+    Ok(Response::new()
+        .add_submessage(bootstrap_sub_msg(&env, &cfg, &bootstrap)?)
+        .add_attribute("action", "resume_bootstrap")
+        .add_attribute("stage", bootstrap.stage.as_str())
+    )
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -178,15 +377,110 @@ pub fn execute(
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    if let Some((category, auth)) = admin_auth(&msg) {
+        if TIMELOCK_DELAYS.may_load(deps.storage, category.as_str())?.unwrap_or(0) > 0 {
+            return Err(ContractError::Timelocked { category: category.as_str().to_string() });
+        }
+        authorize(deps.as_ref(), &env, auth, &info.sender)?;
+    }
+    dispatch(deps, env, info, msg)
+}
+
+/// Timelock category and authorization of the admin messages. Both are checked here for direct calls
+/// and scheduled operations alike, the handlers of these messages do not authorize the sender again.
+pub fn admin_auth(msg: &ExecuteMsg) -> Option<(TimelockCategory, AdminAuth)> {
+    match msg {
+        ExecuteMsg::UpdatePrice { .. }
+        | ExecuteMsg::UpdateOracle { .. } => Some((TimelockCategory::Price, AdminAuth::Role(Role::PriceSetter))),
+        ExecuteMsg::UpdateFees { .. }
+        | ExecuteMsg::UpdateMinCollateralRatio { .. }
+        | ExecuteMsg::UpdateMintLimit { .. }
+        | ExecuteMsg::UpdateKeeperReward { .. } => Some((TimelockCategory::Fees, AdminAuth::Owner)),
+        ExecuteMsg::UpdateMinStknReserve { .. } => Some((TimelockCategory::Fees, AdminAuth::Role(Role::Treasurer))),
+        ExecuteMsg::UpdateCodeIds { .. }
+        | ExecuteMsg::MigrateStocks { .. }
+        | ExecuteMsg::UpdateStakingLockTiers { .. } => Some((TimelockCategory::CodeIds, AdminAuth::Owner)),
+        ExecuteMsg::DelistStock { .. }
+        | ExecuteMsg::ArchiveStock { .. } => Some((TimelockCategory::Listing, AdminAuth::Role(Role::Lister))),
+        ExecuteMsg::WithdrawStkn { .. } => Some((TimelockCategory::Treasury, AdminAuth::Role(Role::Treasurer))),
+        ExecuteMsg::UpdateTimelockDelay { .. } => Some((TimelockCategory::Timelock, AdminAuth::Owner)),
+        ExecuteMsg::CorporateAction(_) => Some((TimelockCategory::CorporateAction, AdminAuth::Owner)),
+        ExecuteMsg::UpdateCircuitBreaker { .. } => Some((TimelockCategory::CircuitBreaker, AdminAuth::Owner)),
+        ExecuteMsg::GrantRole { .. }
+        | ExecuteMsg::RevokeRole { .. } => Some((TimelockCategory::Roles, AdminAuth::Owner)),
+        _ => None,
+    }
+}
+
+fn authorize(
+    deps: Deps,
+    env: &Env,
+    auth: AdminAuth,
+    address: &Addr
+) -> Result<bool, ContractError> {
+    match auth {
+        // authorize owner
+        AdminAuth::Owner => manager::check_owner(deps.querier, env.contract.address.clone(), address.clone()),
+        AdminAuth::Role(role) => check_role(deps.storage, role, address.clone())
+    }
+}
+
+fn dispatch(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateOwner { owner } => execute_update_owner(deps, env, info, owner),
+        ExecuteMsg::ResumeBootstrap {} => execute_resume_bootstrap(deps, env, info),
+        ExecuteMsg::Schedule { msg, eta } => execute_schedule(deps, env, info, *msg, eta),
+        ExecuteMsg::Execute { id } => execute_scheduled(deps, env, id),
+        ExecuteMsg::Cancel { id } => execute_cancel(deps, env, info, id),
+        ExecuteMsg::SetupMultisig { members, threshold, voting_period } => execute_setup_multisig(deps, env, info, members, threshold, voting_period),
+        ExecuteMsg::UpdateMultisig { add, remove, threshold, voting_period } => execute_update_multisig(deps, env, info, add, remove, threshold, voting_period),
+        ExecuteMsg::Propose { title, target, msg } => execute_propose(deps, env, info, title, target, msg),
+        ExecuteMsg::Vote { id, yes } => execute_vote(deps, env, info, id, yes),
+        ExecuteMsg::ExecuteProposal { id } => execute_proposal(deps, env, id),
+        ExecuteMsg::UpdateTimelockDelay { category, delay } => execute_update_timelock_delay(deps, env, info, category, delay),
+        ExecuteMsg::UpdateStakingLockTiers { lock_days, ratios } => execute_update_staking_lock_tiers(deps, env, info, lock_days, ratios),
+        ExecuteMsg::ProposeOwner { owner, expiry } => execute_propose_owner(deps, env, info, owner, expiry),
+        ExecuteMsg::AcceptOwnership {} => execute_accept_ownership(deps, env, info),
+        ExecuteMsg::CancelOwnershipProposal {} => execute_cancel_ownership_proposal(deps, env, info),
+        ExecuteMsg::RenounceOwnership {} => execute_renounce_ownership(deps, env, info),
+        ExecuteMsg::GrantRole { role, address } => execute_grant_role(deps, env, info, role, address),
+        ExecuteMsg::RevokeRole { role, address } => execute_revoke_role(deps, env, info, role, address),
         ExecuteMsg::UpdateEnabled { enabled } => execute_update_enabled(deps, env, info, enabled),
         ExecuteMsg::UpdatePrice { price } => execute_update_price(deps, env, info, price),
-        ExecuteMsg::RemoveStock {id} => execute_remove_stock(deps, env, info, id),
-        ExecuteMsg::RemoveAllStocks {  } => execute_remove_all_stocks(deps, env, info),
-        ExecuteMsg::AddStock{ name, symbol, url} => execute_add_stock(deps, env, info, name, symbol, url),
+        ExecuteMsg::UpdateOracle { oracle_address, max_price_age } => execute_update_oracle(deps, env, info, oracle_address, max_price_age),
+        ExecuteMsg::UpdateFees { buy_pusd_fee_bps, sell_pusd_fee_bps, fee_collector } => execute_update_fees(deps, env, info, buy_pusd_fee_bps, sell_pusd_fee_bps, fee_collector),
+        ExecuteMsg::WithdrawStkn { amount, recipient } => execute_withdraw_stkn(deps, env, info, amount, recipient),
+        ExecuteMsg::UpdateMinStknReserve { amount } => execute_update_min_stkn_reserve(deps, env, info, amount),
+        ExecuteMsg::UpdateKeeperReward { amount } => execute_update_keeper_reward(deps, env, info, amount),
+        ExecuteMsg::SyncDue {} => execute_sync_due(deps, env, info),
+        ExecuteMsg::RecordSync {} => execute_record_sync(deps, env, info),
+        ExecuteMsg::PayKeeperReward {} => execute_pay_keeper_reward(deps, env, info),
+        ExecuteMsg::UpdateMinCollateralRatio { ratio } => execute_update_min_collateral_ratio(deps, env, info, ratio),
+        ExecuteMsg::WithdrawFees { amount } => execute_withdraw_fees(deps, env, info, amount),
+        ExecuteMsg::DelistStock { id, redemption_period } => execute_delist_stock(deps, env, info, id, redemption_period),
+        ExecuteMsg::ArchiveStock { id } => execute_archive_stock(deps, env, info, id),
+        ExecuteMsg::UpdateMintLimit { id, kind, limit } => execute_update_mint_limit(deps, env, info, id, kind, limit),
+        ExecuteMsg::UpdateCircuitBreaker { id, breaker } => execute_update_circuit_breaker(deps, env, info, id, breaker),
+        ExecuteMsg::CorporateAction(action) => execute_corporate_action(deps, env, info, action),
+        ExecuteMsg::Resume { id } => execute_resume(deps, env, info, id),
+        ExecuteMsg::CheckCircuitBreaker { id } => execute_check_circuit_breaker(deps, env, id),
+        ExecuteMsg::UpdateStockSymbol { id, symbol } => execute_update_stock_symbol(deps, env, info, id, symbol),
+        ExecuteMsg::UpdateStockStatus { id, status } => execute_update_stock_status(deps, env, info, id, status),
+        ExecuteMsg::UpdateCodeIds { cw20_code_id, stock_code_id, pool_code_id, staking_code_id, shorting_code_id, trading_code_id, providing_code_id } 
+            => execute_update_code_ids(deps, env, info, cw20_code_id, stock_code_id, pool_code_id, staking_code_id, shorting_code_id, trading_code_id, providing_code_id),
+        ExecuteMsg::MigrateStocks { ids, target, migrate_msg } => execute_migrate_stocks(deps, env, info, ids, target, migrate_msg),
+        ExecuteMsg::AddStock{ name, symbol, url, metadata } => execute_add_stock(deps, env, info, name, symbol, url, metadata.unwrap_or_default()),
+        ExecuteMsg::UpdateTokenMarketing { token, project, description, marketing } => execute_update_token_marketing(deps, env, info, token, project, description, marketing),
+        ExecuteMsg::UploadTokenLogo { token, logo } => execute_upload_token_logo(deps, env, info, token, logo),
+        ExecuteMsg::UpdateStockMetadata { id, metadata } => execute_update_stock_metadata(deps, env, info, id, metadata),
         ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::GlobalSettlement {} => execute_global_settlement(deps, env, info),
+        ExecuteMsg::SettleStocks { limit } => execute_settle_stocks(deps, env, limit),
         ExecuteMsg::MintPusd { id, recipient, amount } => execute_mint_pusd(deps, env, info.sender.clone(), id, recipient, amount),
         ExecuteMsg::MintStock { id, recipient, amount } => execute_mint_stock(deps, env, info.sender.clone(), id, recipient, amount),
         ExecuteMsg::TransferStkn { id, recipient, amount } => execute_transfer_stkn(deps, env, info.sender.clone(), id, recipient, amount),
@@ -194,475 +488,4263 @@ pub fn execute(
 }
 
 
-pub fn execute_update_owner(
+pub fn execute_schedule(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    owner: Addr,
+    msg: ExecuteMsg,
+    eta: u64
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    util::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
-    
-    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
-        exists.owner = owner.clone();
-        Ok(exists)
+    let (category, auth) = admin_auth(&msg).ok_or(ContractError::NotTimelockable {})?;
+    authorize(deps.as_ref(), &env, auth, &info.sender)?;
+
+    let delay = TIMELOCK_DELAYS.may_load(deps.storage, category.as_str())?.unwrap_or(0);
+    let min_eta = env.block.time.seconds() + delay;
+    if eta < min_eta {
+        return Err(ContractError::EtaTooEarly { min_eta });
+    }
+
+    let id = SCHEDULED_OPERATION_COUNT.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+    SCHEDULED_OPERATIONS.save(deps.storage, id, &ScheduledOperation {
+        id,
+        category,
+        msg: to_binary(&msg)?,
+        proposer: info.sender.clone(),
+        scheduled_at: env.block.time.seconds(),
+        eta
     })?;
 
-    Ok(Response::new().add_attribute("action", "update_owner").add_attribute("owner", owner.clone()))
+    Ok(Response::new()
+        .add_attribute("action", "schedule")
+        .add_attribute("id", id.to_string())
+        .add_attribute("category", category.as_str())
+        .add_attribute("eta", eta.to_string())
+    )
 }
 
-pub fn execute_update_enabled (
+/// The operation runs as its proposer, who still has to be authorized for it. A delay raised after
+/// scheduling applies to the operation as well.
+pub fn execute_scheduled(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
-    enabled: bool
+    id: u64
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    util::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
-    
-    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
-        exists.enabled = enabled;
-        Ok(exists)
-    })?;
+    let operation = SCHEDULED_OPERATIONS.load(deps.storage, id)?;
+    let delay = TIMELOCK_DELAYS.may_load(deps.storage, operation.category.as_str())?.unwrap_or(0);
+    let eta = std::cmp::max(operation.eta, operation.scheduled_at.saturating_add(delay));
+    if env.block.time.seconds() < eta {
+        return Err(ContractError::OperationNotReady { eta });
+    }
+    SCHEDULED_OPERATIONS.remove(deps.storage, id);
 
-    Ok(Response::new().add_attribute("action", "update_enabled"))
+    let msg: ExecuteMsg = from_binary(&operation.msg)?;
+    let (_, auth) = admin_auth(&msg).ok_or(ContractError::NotTimelockable {})?;
+    authorize(deps.as_ref(), &env, auth, &operation.proposer)?;
+    let info = MessageInfo { sender: operation.proposer.clone(), funds: vec![] };
+    let response = dispatch(deps, env, info, msg)?;
+
+    Ok(response
+        .add_attribute("scheduled_action", "execute")
+        .add_attribute("scheduled_id", id.to_string())
+    )
 }
-pub fn execute_add_stock(
+
+pub fn execute_cancel(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    name: String,
-    symbol: String,
-    url: String,
+    id: u64
 ) -> Result<Response, ContractError> {
+    let operation = SCHEDULED_OPERATIONS.load(deps.storage, id)?;
+    if operation.proposer != info.sender {
+        // authorize owner
+        manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+    }
+    SCHEDULED_OPERATIONS.remove(deps.storage, id);
 
-    
-    util::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
-    
-    let cfg = CONFIG.load(deps.storage)?;
-    // Instantiate Stock Contract
+    Ok(Response::new()
+        .add_attribute("action", "cancel")
+        .add_attribute("id", id.to_string())
+    )
+}
 
-    let mut sub_msg: Vec<SubMsg> = vec![];
-    
-    sub_msg.push(SubMsg {
-        msg: WasmMsg::Instantiate {
-            code_id: cfg.stock_code_id,
-            funds: vec![],
-            admin: Some(cfg.owner.clone().into()),
-            label: String::from("Stock Token : ") + name.as_str(),
-            msg: to_binary(&StockInstantiateMsg {
-                name,
-                symbol,
-                decimals: 6,
-                initial_balances: vec![],
-                mint: None,
-                marketing: Some(StockInstantiateMarketingInfo {
-                    project: None,
-                    description: None,
-                    logo: Some(Logo::Url(url)),
-                    marketing: Some(cfg.owner.clone().into())
-                }),
+fn multisig_member_count(storage: &dyn Storage) -> u32 {
+    MULTISIG_MEMBERS.keys(storage, None, None, Order::Ascending).count() as u32
+}
 
-                id: cfg.max_stock_id + 1,
-                owner: cfg.owner.clone(),
-                pool_code_id: cfg.pool_code_id,
-                shorting_code_id: cfg.shorting_code_id,
-                trading_code_id: cfg.trading_code_id,
-                providing_code_id: cfg.providing_code_id,
-                cw20_code_id: cfg.cw20_code_id,
-                
-            })?,
-        }.into(),
-        id: INSTANTIATE_STOCK_ID,
-        gas_limit: None,
-        reply_on: ReplyOn::Success,
-    });
+fn check_multisig_member(storage: &dyn Storage, address: &Addr) -> Result<bool, ContractError> {
+    if !MULTISIG_MEMBERS.has(storage, address) {
+        return Err(ContractError::NotMember {});
+    }
+    Ok(true)
+}
+
+pub fn execute_setup_multisig(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    members: Vec<Addr>,
+    threshold: u32,
+    voting_period: u64
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+    if MULTISIG_CONFIG.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::MultisigAlreadySet {});
+    }
 
-    Ok(Response::new().add_submessages(sub_msg))
+    for member in members.iter() {
+        let member = deps.api.addr_validate(member.as_str())?;
+        MULTISIG_MEMBERS.save(deps.storage, &member, &true)?;
+    }
+    let member_count = multisig_member_count(deps.storage);
+    if threshold == 0 || threshold > member_count {
+        return Err(ContractError::InvalidThreshold { members: member_count });
+    }
+    MULTISIG_CONFIG.save(deps.storage, &MultisigConfig { threshold, voting_period })?;
 
+    Ok(Response::new()
+        .add_attribute("action", "setup_multisig")
+        .add_attribute("members", member_count.to_string())
+        .add_attribute("threshold", threshold.to_string())
+    )
 }
 
-pub fn execute_update_price(
+pub fn execute_update_multisig(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    price: Uint128
+    add: Vec<Addr>,
+    remove: Vec<Addr>,
+    threshold: Option<u32>,
+    voting_period: Option<u64>
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    util::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
-        
-    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
-        exists.price = price;
-        Ok(exists)
-    })?;
+    // only a passed proposal sent by the manager itself
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut cfg = MULTISIG_CONFIG.may_load(deps.storage)?.ok_or(ContractError::MultisigNotSet {})?;
 
-    Ok(Response::new().add_attribute("action", "update_price")
-    .add_attribute("price", price)
-)
+    for member in add.iter() {
+        let member = deps.api.addr_validate(member.as_str())?;
+        MULTISIG_MEMBERS.save(deps.storage, &member, &true)?;
+    }
+    for member in remove.iter() {
+        MULTISIG_MEMBERS.remove(deps.storage, member);
+    }
+    if let Some(threshold) = threshold {
+        cfg.threshold = threshold;
+    }
+    if let Some(voting_period) = voting_period {
+        cfg.voting_period = voting_period;
+    }
+
+    let member_count = multisig_member_count(deps.storage);
+    if cfg.threshold == 0 || cfg.threshold > member_count {
+        return Err(ContractError::InvalidThreshold { members: member_count });
+    }
+    MULTISIG_CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_multisig")
+        .add_attribute("members", member_count.to_string())
+        .add_attribute("threshold", cfg.threshold.to_string())
+    )
 }
 
-pub fn execute_remove_stock(
+pub fn execute_propose(
     deps: DepsMut,
-    env: Env, 
+    env: Env,
     info: MessageInfo,
-    id: u32
-) -> Result<Response, ContractError>{
-    util::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
-    
-    STOCKS.remove(deps.storage, id);
+    title: String,
+    target: Option<Addr>,
+    msg: Binary
+) -> Result<Response, ContractError> {
+    let cfg = MULTISIG_CONFIG.may_load(deps.storage)?.ok_or(ContractError::MultisigNotSet {})?;
+    check_multisig_member(deps.storage, &info.sender)?;
+
+    let target = match target {
+        Some(target) => {
+            // protocol contracts like STKN or PUSD are not administered by proposals
+            let stock_contract = ADDRESS_ROLES
+                .may_load(deps.storage, &target)?
+                .map_or(false, |address_role| address_role.stock_id.is_some());
+            if target != env.contract.address && !stock_contract {
+                return Err(ContractError::InvalidInput {});
+            }
+            target
+        },
+        None => env.contract.address.clone()
+    };
+
+    let id = PROPOSAL_COUNT.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+    let mut proposal = Proposal {
+        id,
+        title,
+        proposer: info.sender.clone(),
+        target: target.clone(),
+        msg,
+        expires: env.block.time.seconds() + cfg.voting_period,
+        yes_votes: 0,
+        no_votes: 0,
+        status: ProposalStatus::Open
+    };
+    // the proposer votes yes
+    PROPOSAL_VOTES.save(deps.storage, (id, &info.sender), &true)?;
+    tally_proposal(deps.storage, &cfg, &mut proposal)?;
+    PROPOSALS.save(deps.storage, id, &proposal)?;
+
     Ok(Response::new()
-        .add_attribute("action", "remove_stock")
+        .add_attribute("action", "propose")
         .add_attribute("id", id.to_string())
+        .add_attribute("target", target)
+        .add_attribute("status", proposal.status.as_str())
     )
 }
 
-pub fn execute_remove_all_stocks(
+pub fn execute_vote(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo
+    info: MessageInfo,
+    id: u64,
+    yes: bool
 ) -> Result<Response, ContractError> {
-    // authorize owner
-    util::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
-    
-
-    let stocks:StdResult<Vec<_>> = STOCKS
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|item| map_stock(item))
-        .collect();
+    let cfg = MULTISIG_CONFIG.may_load(deps.storage)?.ok_or(ContractError::MultisigNotSet {})?;
+    check_multisig_member(deps.storage, &info.sender)?;
 
-    if stocks.is_err() {
-        return Err(ContractError::Map2ListFailed {})
+    let mut proposal = PROPOSALS.load(deps.storage, id)?;
+    let status = proposal.current_status(env.block.time.seconds());
+    if status != ProposalStatus::Open {
+        return Err(ContractError::ProposalNotOpen { status: status.as_str().to_string() });
     }
-    
-    for item in stocks.unwrap() {
-        STOCKS.remove(deps.storage, item.id);
+    if PROPOSAL_VOTES.has(deps.storage, (id, &info.sender)) {
+        return Err(ContractError::AlreadyVoted {});
     }
-    
-    Ok(Response::new().add_attribute("action", "remove_all_stock"))
+    PROPOSAL_VOTES.save(deps.storage, (id, &info.sender), &yes)?;
+    tally_proposal(deps.storage, &cfg, &mut proposal)?;
+    PROPOSALS.save(deps.storage, id, &proposal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "vote")
+        .add_attribute("id", id.to_string())
+        .add_attribute("voter", info.sender.clone())
+        .add_attribute("yes", yes.to_string())
+        .add_attribute("status", proposal.status.as_str())
+    )
 }
 
+/// Counts the votes of the current members against the current threshold, so members removed
+/// after voting no longer count and a raised threshold applies to open and passed proposals alike
+fn tally_proposal(
+    storage: &dyn Storage,
+    cfg: &MultisigConfig,
+    proposal: &mut Proposal
+) -> StdResult<()> {
+    proposal.yes_votes = 0;
+    proposal.no_votes = 0;
+    for vote in PROPOSAL_VOTES.prefix(proposal.id).range(storage, None, None, Order::Ascending) {
+        let (voter, yes) = vote?;
+        if !MULTISIG_MEMBERS.has(storage, &voter) {
+            continue;
+        }
+        if yes {
+            proposal.yes_votes += 1;
+        } else {
+            proposal.no_votes += 1;
+        }
+    }
+    proposal.status = if proposal.yes_votes >= cfg.threshold {
+        ProposalStatus::Passed
+    } else if proposal.no_votes > multisig_member_count(storage).saturating_sub(cfg.threshold) {
+        ProposalStatus::Rejected
+    } else {
+        ProposalStatus::Open
+    };
+    Ok(())
+}
 
-pub fn execute_receive(
-    deps: DepsMut, 
+pub fn execute_proposal(
+    deps: DepsMut,
     env: Env,
-    info: MessageInfo, 
-    wrapper: Cw20ReceiveMsg
+    id: u64
 ) -> Result<Response, ContractError> {
-    
-    util::check_enabled(deps.querier, env.contract.address.clone())?;
+    let cfg = MULTISIG_CONFIG.may_load(deps.storage)?.ok_or(ContractError::MultisigNotSet {})?;
+    let mut proposal = PROPOSALS.load(deps.storage, id)?;
+    let status = proposal.current_status(env.block.time.seconds());
+    if status != ProposalStatus::Passed && status != ProposalStatus::Open {
+        return Err(ContractError::ProposalNotPassed {});
+    }
+    tally_proposal(deps.storage, &cfg, &mut proposal)?;
+    if proposal.status != ProposalStatus::Passed {
+        return Err(ContractError::ProposalNotPassed {});
+    }
+    proposal.status = ProposalStatus::Executed;
+    PROPOSALS.save(deps.storage, id, &proposal)?;
 
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: proposal.target.clone().into(),
+            funds: vec![],
+            msg: proposal.msg.clone(),
+        }))
+        .add_attribute("action", "execute_proposal")
+        .add_attribute("id", id.to_string())
+        .add_attribute("target", proposal.target)
+    )
+}
+
+pub fn execute_update_timelock_delay(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    category: TimelockCategory,
+    delay: u64
+) -> Result<Response, ContractError> {
+
+    if delay == 0 {
+        TIMELOCK_DELAYS.remove(deps.storage, category.as_str());
+    } else {
+        TIMELOCK_DELAYS.save(deps.storage, category.as_str(), &delay)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_timelock_delay")
+        .add_attribute("category", category.as_str())
+        .add_attribute("delay", delay.to_string())
+    )
+}
+
+pub fn execute_update_staking_lock_tiers(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    lock_days: Vec<u64>,
+    ratios: Vec<u64>
+) -> Result<Response, ContractError> {
+    check_bootstrapped(deps.storage)?;
+
+    if lock_days.is_empty() || lock_days.len() != ratios.len() {
+        return Err(ContractError::InvalidInput {});
+    }
     let cfg = CONFIG.load(deps.storage)?;
-    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
-    let user_addr = &deps.api.addr_validate(&wrapper.sender)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cfg.staking_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&StakingExecuteMsg::UpdateLockTiers {
+                lock_days: lock_days.clone(),
+                ratios: ratios.clone()
+            })?,
+        }))
+        .add_attribute("action", "update_staking_lock_tiers")
+        .add_attribute("lock_days", lock_days.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(","))
+        .add_attribute("ratios", ratios.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(","))
+    )
+}
+
+pub fn execute_propose_owner(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: Addr,
+    expiry: Option<Expiration>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+
+    let owner = deps.api.addr_validate(owner.as_str())?;
+    if let Some(expiry) = expiry {
+        if expiry.is_expired(&env.block) {
+            return Err(ContractError::OwnershipProposalExpired {});
+        }
+    }
+
+    PENDING_OWNER.save(deps.storage, &PendingOwner {
+        owner: owner.clone(),
+        expiry
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_owner")
+        .add_attribute("pending_owner", owner.clone())
+        .add_attribute("expiry", expiry.map_or(String::from("none"), |e| e.to_string()))
+    )
+}
+
+pub fn execute_accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    let pending = PENDING_OWNER.may_load(deps.storage)?.ok_or(ContractError::NoPendingOwner {})?;
+
+    if pending.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(expiry) = pending.expiry {
+        if expiry.is_expired(&env.block) {
+            return Err(ContractError::OwnershipProposalExpired {});
+        }
+    }
+
+    let mut cfg = CONFIG.load(deps.storage)?;
+    let previous_owner = cfg.owner.replace(pending.owner.clone());
+    CONFIG.save(deps.storage, &cfg)?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_ownership")
+        .add_attribute("previous_owner", previous_owner.map_or(String::from("none"), |owner| owner.to_string()))
+        .add_attribute("owner", pending.owner)
+    )
+}
+
+pub fn execute_cancel_ownership_proposal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+
+    let pending = PENDING_OWNER.may_load(deps.storage)?.ok_or(ContractError::NoPendingOwner {})?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_ownership_proposal")
+        .add_attribute("pending_owner", pending.owner)
+    )
+}
+
+/// Removes the owner for good. Owner-only actions, the multisig included, can no longer be authorized
+/// and no new owner can be proposed. Roles granted before keep working.
+pub fn execute_renounce_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.owner = None;
+        Ok(exists)
+    })?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "renounce_ownership")
+        .add_attribute("previous_owner", info.sender.clone())
+    )
+}
+
+pub fn execute_grant_role(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    role: Role,
+    address: Addr
+) -> Result<Response, ContractError> {
+
+    let address = deps.api.addr_validate(address.as_str())?;
+    ROLES.save(deps.storage, (role.as_str(), &address), &true)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "grant_role")
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address)
+    )
+}
+
+pub fn execute_revoke_role(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    role: Role,
+    address: Addr
+) -> Result<Response, ContractError> {
+
+    ROLES.remove(deps.storage, (role.as_str(), &address));
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_role")
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address)
+    )
+}
+
+/// The owner implicitly holds every role.
+pub fn check_role(
+    storage: &dyn Storage,
+    role: Role,
+    address: Addr
+) -> Result<bool, ContractError> {
+    let cfg = CONFIG.load(storage)?;
+    if cfg.owner.as_ref() == Some(&address) || ROLES.has(storage, (role.as_str(), &address)) {
+        return Ok(true);
+    }
+    Err(ContractError::MissingRole { role: role.as_str().to_string() })
+}
+
+pub fn execute_update_enabled (
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool
+) -> Result<Response, ContractError> {
+    // authorize pauser
+    check_role(deps.storage, Role::Pauser, info.sender.clone())?;
+    
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.enabled = enabled;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new().add_attribute("action", "update_enabled"))
+}
+pub fn execute_add_stock(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    symbol: String,
+    url: String,
+    metadata: StockMetadata,
+) -> Result<Response, ContractError> {
+
     
+    check_role(deps.storage, Role::Lister, info.sender.clone())?;
+    check_not_settled(deps.storage)?;
+    check_bootstrapped(deps.storage)?;
+    metadata.validate()?;
+    if !is_valid_symbol(&symbol) {
+        return Err(ContractError::InvalidSymbol { symbol });
+    }
+
+    let key = symbol_key(&symbol);
+    if STOCK_SYMBOLS.has(deps.storage, &key) {
+        return Err(ContractError::DuplicateSymbol { symbol });
+    }
+
+    // Reserve the id and the symbol, the whole transaction reverts if the instantiation fails
+    let mut cfg = CONFIG.load(deps.storage)?;
+    // the stock subcontracts are created with the owner as their owner
+    let owner = cfg.owner.clone().ok_or(ContractError::OwnershipRenounced {})?;
+    cfg.max_stock_id += 1;
+    CONFIG.save(deps.storage, &cfg)?;
+    let stock_id = cfg.max_stock_id;
+    STOCK_SYMBOLS.save(deps.storage, &key, &stock_id)?;
+
+    let reply_id = INSTANTIATE_STOCK_ID + stock_id as u64;
+    let description = metadata.marketing_description();
+    PENDING_STOCKS.save(deps.storage, reply_id, &PendingStock {
+        id: stock_id,
+        symbol: symbol.clone(),
+        metadata
+    })?;
+
+    // Instantiate Stock Contract
+
+    let mut sub_msg: Vec<SubMsg> = vec![];
     
-    if wrapper.amount == Uint128::zero() {
+    sub_msg.push(SubMsg {
+        msg: WasmMsg::Instantiate {
+            code_id: cfg.stock_code_id,
+            funds: vec![],
+            // manager is admin so that MigrateStocks can upgrade it
+            admin: Some(env.contract.address.clone().into()),
+            label: String::from("Stock Token : ") + name.as_str(),
+            msg: to_binary(&StockInstantiateMsg {
+                name,
+                symbol,
+                decimals: 6,
+                initial_balances: vec![],
+                mint: None,
+                marketing: Some(StockInstantiateMarketingInfo {
+                    project: None,
+                    description,
+                    logo: Some(Logo::Url(url)),
+                    // manager pushes metadata updates
+                    marketing: Some(env.contract.address.clone().into())
+                }),
+
+                id: stock_id,
+                owner,
+                pool_code_id: cfg.pool_code_id,
+                shorting_code_id: cfg.shorting_code_id,
+                trading_code_id: cfg.trading_code_id,
+                providing_code_id: cfg.providing_code_id,
+                cw20_code_id: cfg.cw20_code_id,
+                
+            })?,
+        }.into(),
+        id: reply_id,
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    });
+
+    Ok(Response::new()
+        .add_submessages(sub_msg)
+        .add_attribute("action", "add_stock")
+        .add_attribute("id", stock_id.to_string())
+    )
+
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_update_code_ids(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    cw20_code_id: Option<u64>,
+    stock_code_id: Option<u64>,
+    pool_code_id: Option<u64>,
+    staking_code_id: Option<u64>,
+    shorting_code_id: Option<u64>,
+    trading_code_id: Option<u64>,
+    providing_code_id: Option<u64>
+) -> Result<Response, ContractError> {
+
+    let cfg = CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.cw20_code_id = cw20_code_id.unwrap_or(exists.cw20_code_id);
+        exists.stock_code_id = stock_code_id.unwrap_or(exists.stock_code_id);
+        exists.pool_code_id = pool_code_id.unwrap_or(exists.pool_code_id);
+        exists.staking_code_id = staking_code_id.unwrap_or(exists.staking_code_id);
+        exists.shorting_code_id = shorting_code_id.unwrap_or(exists.shorting_code_id);
+        exists.trading_code_id = trading_code_id.unwrap_or(exists.trading_code_id);
+        exists.providing_code_id = providing_code_id.unwrap_or(exists.providing_code_id);
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_code_ids")
+        .add_attribute("cw20_code_id", cfg.cw20_code_id.to_string())
+        .add_attribute("stock_code_id", cfg.stock_code_id.to_string())
+        .add_attribute("pool_code_id", cfg.pool_code_id.to_string())
+        .add_attribute("staking_code_id", cfg.staking_code_id.to_string())
+        .add_attribute("shorting_code_id", cfg.shorting_code_id.to_string())
+        .add_attribute("trading_code_id", cfg.trading_code_id.to_string())
+        .add_attribute("providing_code_id", cfg.providing_code_id.to_string())
+    )
+}
+
+pub fn subcontract_address(stock_info: &StockInfo, target: Subcontract) -> Addr {
+    match target {
+        Subcontract::Stock => stock_info.stock_address.clone(),
+        Subcontract::Pool => stock_info.pool_address.clone(),
+        Subcontract::Shorting => stock_info.shorting_address.clone(),
+        Subcontract::Trading => stock_info.trading_address.clone(),
+        Subcontract::Providing => stock_info.providing_address.clone(),
+    }
+}
+
+pub fn subcontract_code_id(cfg: &Config, target: Subcontract) -> u64 {
+    match target {
+        Subcontract::Stock => cfg.stock_code_id,
+        Subcontract::Pool => cfg.pool_code_id,
+        Subcontract::Shorting => cfg.shorting_code_id,
+        Subcontract::Trading => cfg.trading_code_id,
+        Subcontract::Providing => cfg.providing_code_id,
+    }
+}
+
+pub fn execute_migrate_stocks(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    ids: Vec<u32>,
+    target: Subcontract,
+    migrate_msg: Binary
+) -> Result<Response, ContractError> {
+
+    if ids.is_empty() {
+        return Err(ContractError::InvalidInput {});
+    }
+    let cfg = CONFIG.load(deps.storage)?;
+    let new_code_id = subcontract_code_id(&cfg, target);
+
+    let mut messages:Vec<CosmosMsg> = vec![];
+    for id in ids.iter() {
+        let stock_info = STOCKS.load(deps.storage, *id)?;
+        let address = subcontract_address(&stock_info, target);
+        // a migration the chain would reject fails here with the contract named
+        let contract_info: ContractInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::ContractInfo {
+            contract_addr: address.clone().into(),
+        }))?;
+        if contract_info.admin.as_deref() != Some(env.contract.address.as_str()) {
+            return Err(ContractError::NotAdmin { address: address.into() });
+        }
+        messages.push(CosmosMsg::Wasm(WasmMsg::Migrate {
+            contract_addr: address.into(),
+            new_code_id,
+            msg: migrate_msg.clone(),
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "migrate_stocks")
+        .add_attribute("target", target.as_str())
+        .add_attribute("code_id", new_code_id.to_string())
+        .add_attribute("ids", ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(","))
+    )
+}
+
+pub fn execute_update_price(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    price: Uint128
+) -> Result<Response, ContractError> {
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.price = price;
+        exists.price_updated_at = env.block.time.seconds();
+        Ok(exists)
+    })?;
+
+    Ok(Response::new().add_attribute("action", "update_price")
+    .add_attribute("price", price)
+)
+}
+
+pub fn execute_update_oracle(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    oracle_address: Option<Addr>,
+    max_price_age: Option<u64>
+) -> Result<Response, ContractError> {
+
+    let oracle_address = oracle_address
+        .map(|addr| deps.api.addr_validate(addr.as_str()))
+        .transpose()?;
+
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.oracle_address = oracle_address.clone();
+        exists.max_price_age = max_price_age;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_oracle")
+        .add_attribute("oracle_address", oracle_address.map_or(String::from("none"), |a| a.to_string()))
+        .add_attribute("max_price_age", max_price_age.map_or(String::from("none"), |a| a.to_string()))
+    )
+}
+
+pub fn execute_update_fees(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    buy_pusd_fee_bps: u64,
+    sell_pusd_fee_bps: u64,
+    fee_collector: Addr
+) -> Result<Response, ContractError> {
+
+    if buy_pusd_fee_bps > MAX_FEE_BPS || sell_pusd_fee_bps > MAX_FEE_BPS {
+        return Err(ContractError::InvalidFee { max_bps: MAX_FEE_BPS });
+    }
+    let fee_collector = deps.api.addr_validate(fee_collector.as_str())?;
+
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.buy_pusd_fee_bps = buy_pusd_fee_bps;
+        exists.sell_pusd_fee_bps = sell_pusd_fee_bps;
+        exists.fee_collector = fee_collector.clone();
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_fees")
+        .add_attribute("buy_pusd_fee_bps", buy_pusd_fee_bps.to_string())
+        .add_attribute("sell_pusd_fee_bps", sell_pusd_fee_bps.to_string())
+        .add_attribute("fee_collector", fee_collector)
+    )
+}
+
+pub fn execute_withdraw_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Option<Uint128>
+) -> Result<Response, ContractError> {
+    check_role(deps.storage, Role::Treasurer, info.sender.clone())?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let accrued = ACCRUED_FEES.load(deps.storage)?;
+    let amount = amount.unwrap_or(accrued);
+    if amount.is_zero() {
         return Err(ContractError::InvalidInput {});
     }
+    if amount > accrued {
+        return Err(ContractError::InsufficientFees {});
+    }
+    ACCRUED_FEES.save(deps.storage, &(accrued - amount))?;
+    let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+    record_reserve(deps.storage, &env, "withdraw_fees", &cfg.fee_collector, amount, balance - amount)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cfg.stkn_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: cfg.fee_collector.clone().into(),
+                amount
+            })?,
+        }))
+        .add_attribute("action", "withdraw_fees")
+        .add_attribute("recipient", cfg.fee_collector)
+        .add_attribute("amount", amount)
+    )
+}
+
+pub fn execute_withdraw_stkn(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    amount: Uint128,
+    recipient: Addr
+) -> Result<Response, ContractError> {
+    check_not_settled(deps.storage)?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let recipient = deps.api.addr_validate(recipient.as_str())?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidInput {});
+    }
+
+    let available = get_available_stkn(deps.as_ref(), &env, &cfg)?;
+    if available < amount.checked_add(cfg.min_stkn_reserve).map_err(StdError::overflow)? {
+        return Err(ContractError::BelowMinReserve {});
+    }
+
+    let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+    record_reserve(deps.storage, &env, "withdraw", &recipient, amount, balance - amount)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cfg.stkn_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.clone().into(),
+                amount
+            })?,
+        }))
+        .add_attribute("action", "withdraw_stkn")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount)
+    )
+}
+
+pub fn execute_update_keeper_reward(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.keeper_reward = amount;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_keeper_reward")
+        .add_attribute("amount", amount)
+    )
+}
+
+/// Active stocks whose pool was not synced within providing_sync_interval. At most MAX_SCAN stocks
+/// are read, starting after SYNC_CURSOR and wrapping around. Returns the due stocks, the last stock read
+/// and how many were read.
+fn due_for_sync(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config,
+    limit: usize
+) -> StdResult<(Vec<DueStock>, Option<u32>, usize)> {
+    let now = env.block.time.seconds();
+    let interval = std::cmp::max(cfg.providing_sync_interval, MIN_SYNC_INTERVAL);
+    let cursor = SYNC_CURSOR.may_load(deps.storage)?;
+    let stocks = STOCKS
+        .range(deps.storage, cursor.map(Bound::exclusive), None, Order::Ascending)
+        .chain(cursor.into_iter().flat_map(move |cursor| {
+            STOCKS.range(deps.storage, None, Some(Bound::inclusive(cursor)), Order::Ascending)
+        }));
+
+    let mut due = vec![];
+    let mut last_scanned = None;
+    let mut scanned = 0;
+    for item in stocks.take(MAX_SCAN) {
+        if due.len() >= limit {
+            break;
+        }
+        let (id, stock_info) = item?;
+        last_scanned = Some(id);
+        scanned += 1;
+        if stock_info.status != StockStatus::Active {
+            continue;
+        }
+        let last_sync = LAST_SYNCS.may_load(deps.storage, id)?;
+        if last_sync.as_ref().map_or(true, |last_sync| now >= last_sync.time.saturating_add(interval)) {
+            due.push(DueStock {
+                id,
+                pool_address: stock_info.pool_address,
+                last_sync
+            });
+        }
+    }
+    Ok((due, last_scanned, scanned))
+}
+
+/// Keeper reward for synced pools, nothing when it would cut into the minimum reserve
+fn keeper_reward(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config,
+    synced: usize
+) -> Result<Uint128, ContractError> {
+    let reward = cfg.keeper_reward * Uint128::from(synced as u128);
+    if reward.is_zero() || get_available_stkn(deps, env, cfg)? < reward + cfg.min_stkn_reserve {
+        return Ok(Uint128::zero());
+    }
+    Ok(reward)
+}
+
+/// Each pool is synced in a submessage that is skipped when it fails. Pools report their sync with
+/// RecordSync, the reward for the reported ones is paid by PayKeeperReward after all of them.
+pub fn execute_sync_due(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    manager::check_enabled(deps.querier, env.contract.address.clone())?;
+    check_not_settled(deps.storage)?;
+    check_bootstrapped(deps.storage)?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let (due, last_scanned, scanned) = due_for_sync(deps.as_ref(), &env, &cfg, MAX_LIMIT as usize)?;
+    // a full scan budget without due pools still moves the cursor on
+    if due.is_empty() && scanned < MAX_SCAN {
+        return Err(ContractError::NothingToSync {});
+    }
+    if let Some(last_scanned) = last_scanned {
+        SYNC_CURSOR.save(deps.storage, &last_scanned)?;
+    }
+    KEEPER_RUN.save(deps.storage, &KeeperRun { keeper: info.sender.clone(), synced: 0 })?;
+
+    let mut messages: Vec<SubMsg> = vec![];
+    let mut trips: Vec<Event> = vec![];
+    for stock in due.iter() {
+        // a stock tripped here is paused and its pool sync fails and is skipped
+        trips.extend(record_circuit_breaker(deps.branch(), &env, stock.id)?);
+        messages.push(SubMsg::reply_on_error(WasmMsg::Execute {
+            contract_addr: stock.pool_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&PoolExecuteMsg::SyncPrice {})?,
+        }, SYNC_REPLY_ID + stock.id as u64));
+    }
+    messages.push(SubMsg::new(WasmMsg::Execute {
+        contract_addr: env.contract.address.clone().into(),
+        funds: vec![],
+        msg: to_binary(&ExecuteMsg::PayKeeperReward {})?,
+    }));
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_events(trips)
+        .add_attribute("action", "sync_due")
+        .add_attribute("ids", due.iter().map(|stock| stock.id.to_string()).collect::<Vec<_>>().join(","))
+        .add_attribute("keeper", info.sender.clone())
+    )
+}
+
+pub fn execute_record_sync(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    let id = match ADDRESS_ROLES.may_load(deps.storage, &info.sender)? {
+        Some(AddressRole { stock_id: Some(id), role: ContractRole::Pool }) => id,
+        _ => return Err(ContractError::Unauthorized {})
+    };
+    LAST_SYNCS.save(deps.storage, id, &SyncRecord {
+        height: env.block.height,
+        time: env.block.time.seconds()
+    })?;
+    // syncs outside of SyncDue, e.g. by the owner, only reset the interval
+    if let Some(mut run) = KEEPER_RUN.may_load(deps.storage)? {
+        run.synced += 1;
+        KEEPER_RUN.save(deps.storage, &run)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "record_sync")
+        .add_attribute("id", id.to_string())
+    )
+}
+
+pub fn execute_pay_keeper_reward(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    let run = KEEPER_RUN.load(deps.storage)?;
+    KEEPER_RUN.remove(deps.storage);
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let reward = keeper_reward(deps.as_ref(), &env, &cfg, run.synced as usize)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !reward.is_zero() {
+        let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+        record_reserve(deps.storage, &env, "keeper_reward", &run.keeper, reward, balance - reward)?;
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cfg.stkn_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: run.keeper.clone().into(),
+                amount: reward
+            })?,
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "pay_keeper_reward")
+        .add_attribute("keeper", run.keeper)
+        .add_attribute("synced", run.synced.to_string())
+        .add_attribute("reward", reward)
+    )
+}
+
+pub fn execute_update_min_stkn_reserve(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.min_stkn_reserve = amount;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_min_stkn_reserve")
+        .add_attribute("amount", amount)
+    )
+}
+
+pub fn execute_update_min_collateral_ratio(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    ratio: Option<Decimal>
+) -> Result<Response, ContractError> {
+
+    CONFIG.update(deps.storage, |mut exists| -> StdResult<_> {
+        exists.min_collateral_ratio = ratio;
+        Ok(exists)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_min_collateral_ratio")
+        .add_attribute("ratio", ratio.map_or(String::from("none"), |r| r.to_string()))
+    )
+}
+
+pub fn get_pusd_supply(deps: Deps, cfg: &Config) -> StdResult<Uint128> {
+    let token_info: TokenInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: cfg.pusd_address.clone().into(),
+        msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+    }))?;
+    Ok(token_info.total_supply)
+}
+
+/// STKN reserve valued at Config.price divided by the PUSD supply
+pub fn get_collateral_ratio(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config,
+    pusd_supply: Uint128
+) -> Result<Option<Decimal>, ContractError> {
+    if pusd_supply.is_zero() {
+        return Ok(None);
+    }
+    let reserve_value = get_available_stkn(deps, env, cfg)?.multiply_ratio(cfg.price, NORMAL_DECIMAL);
+    Ok(Some(Decimal::from_ratio(reserve_value, pusd_supply)))
+}
+
+fn record_reserve(
+    storage: &mut dyn Storage,
+    env: &Env,
+    action: &str,
+    address: &Addr,
+    amount: Uint128,
+    balance: Uint128
+) -> StdResult<()> {
+    let count = RESERVE_HISTORY_COUNT.load(storage)? + 1;
+    RESERVE_HISTORY.save(storage, count, &ReserveRecord {
+        action: action.to_string(),
+        address: address.clone(),
+        amount,
+        balance,
+        time: env.block.time.seconds()
+    })?;
+    RESERVE_HISTORY_COUNT.save(storage, &count)
+}
+
+/// STKN held by the manager minus the accrued fees
+pub fn get_available_stkn(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config
+) -> Result<Uint128, ContractError> {
+    let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+    let accrued = ACCRUED_FEES.load(deps.storage)?;
+    Ok(balance.saturating_sub(accrued))
+}
+
+fn is_price_fresh(cfg: &Config, env: &Env, updated_at: u64) -> bool {
+    match cfg.max_price_age {
+        Some(max_age) => env.block.time.seconds().saturating_sub(updated_at) <= max_age,
+        None => true
+    }
+}
+
+/// Oracle price when it is set and fresh, otherwise the manual price if that is fresh.
+pub fn get_swap_price(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config
+) -> Result<PriceResponse, ContractError> {
+    if let Some(oracle_address) = cfg.oracle_address.clone() {
+        if let Ok(oracle) = manager::get_oracle_price(deps.querier, oracle_address) {
+            if !oracle.price.is_zero() && is_price_fresh(cfg, env, oracle.last_updated) {
+                return Ok(PriceResponse {
+                    price: oracle.price,
+                    source: String::from("oracle"),
+                    updated_at: oracle.last_updated
+                });
+            }
+        }
+    }
+
+    if cfg.price.is_zero() || !is_price_fresh(cfg, env, cfg.price_updated_at) {
+        return Err(ContractError::StalePrice {});
+    }
+    Ok(PriceResponse {
+        price: cfg.price,
+        source: String::from("manual"),
+        updated_at: cfg.price_updated_at
+    })
+}
+
+/// Latest oracle or manual price regardless of its age
+fn get_last_known_price(
+    deps: Deps,
+    cfg: &Config
+) -> Result<PriceResponse, ContractError> {
+    let oracle = cfg.oracle_address.clone()
+        .and_then(|oracle_address| manager::get_oracle_price(deps.querier, oracle_address).ok())
+        .filter(|oracle| !oracle.price.is_zero());
+    match oracle {
+        Some(oracle) if cfg.price.is_zero() || oracle.last_updated >= cfg.price_updated_at => Ok(PriceResponse {
+            price: oracle.price,
+            source: String::from("oracle"),
+            updated_at: oracle.last_updated
+        }),
+        _ if !cfg.price.is_zero() => Ok(PriceResponse {
+            price: cfg.price,
+            source: String::from("manual"),
+            updated_at: cfg.price_updated_at
+        }),
+        _ => Err(ContractError::StalePrice {})
+    }
+}
+
+pub fn execute_delist_stock(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    id: u32,
+    redemption_period: u64
+) -> Result<Response, ContractError> {
+    check_not_settled(deps.storage)?;
+
+    let mut stock_info = STOCKS.load(deps.storage, id)?;
+    if stock_info.status == StockStatus::Delisted {
+        return Err(ContractError::StockDelisted { id });
+    }
+
+    let stock_response: StockConfigResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: stock_info.stock_address.clone().into(),
+        msg: to_binary(&StockQueryMsg::Config {})?,
+    }))?;
+    let token_info: TokenInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: stock_info.stock_address.clone().into(),
+        msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+    }))?;
+    let delisting = Delisting {
+        time: env.block.time.seconds(),
+        price: stock_response.price,
+        supply: token_info.total_supply,
+        redeemed: Uint128::zero(),
+        redemption_end: env.block.time.seconds()
+            .checked_add(redemption_period)
+            .ok_or(ContractError::InvalidInput {})?
+    };
+    DELISTINGS.save(deps.storage, id, &delisting)?;
+
+    stock_info.status = StockStatus::Delisted;
+    STOCKS.save(deps.storage, id, &stock_info)?;
+
+    let messages: Vec<CosmosMsg> = [&stock_info.pool_address, &stock_info.trading_address]
+        .iter()
+        .map(|address| -> StdResult<_> {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: address.to_string(),
+                funds: vec![],
+                msg: to_binary(&SubcontractExecuteMsg::SetWithdrawOnly {})?,
+            }))
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "delist_stock")
+        .add_attribute("id", id.to_string())
+        .add_attribute("price", delisting.price)
+        .add_attribute("redemption_end", delisting.redemption_end.to_string())
+    )
+}
+
+pub fn execute_archive_stock(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    id: u32
+) -> Result<Response, ContractError> {
+
+    let stock_info = STOCKS.load(deps.storage, id)?;
+    let delisting = DELISTINGS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::StockNotDelisted { id })?;
+    if env.block.time.seconds() <= delisting.redemption_end {
+        return Err(ContractError::RedemptionOpen { id });
+    }
+
+    STOCKS.remove(deps.storage, id);
+    DELISTINGS.remove(deps.storage, id);
+    STOCK_SYMBOLS.remove(deps.storage, &symbol_key(&stock_info.symbol));
+    unregister_stock_addresses(deps.storage, &stock_info);
+    CIRCUIT_BREAKERS.remove(deps.storage, id);
+    BREAKER_WINDOWS.remove(deps.storage, id);
+    BREAKER_TRIPS.remove(deps.storage, id);
+    STOCK_COUNT.update(deps.storage, |count| -> StdResult<_> { Ok(count.saturating_sub(1)) })?;
+
+    ARCHIVED_STOCKS.save(deps.storage, id, &ArchivedStock {
+        stock: stock_info,
+        delisting,
+        archived_at: env.block.time.seconds()
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "archive_stock")
+        .add_attribute("id", id.to_string())
+    )
+}
+
+pub fn execute_update_mint_limit(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    id: u32,
+    kind: MintKind,
+    limit: MintLimit
+) -> Result<Response, ContractError> {
+
+    if limit.window_limit.is_some() && limit.window == 0 {
+        return Err(ContractError::InvalidInput {});
+    }
+    MINT_LIMITS.save(deps.storage, (id, kind.as_str()), &limit)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_mint_limit")
+        .add_attribute("id", id.to_string())
+        .add_attribute("kind", kind.as_str())
+    )
+}
+
+/// Renames the stock in the manager registry only, cw20 has no message to change the token symbol
+pub fn execute_update_stock_symbol(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    id: u32,
+    symbol: String
+) -> Result<Response, ContractError> {
+    check_role(deps.storage, Role::Lister, info.sender.clone())?;
+    if !is_valid_symbol(&symbol) {
+        return Err(ContractError::InvalidSymbol { symbol });
+    }
+
+    let mut stock_info = STOCKS.load(deps.storage, id)?;
+    let old_key = symbol_key(&stock_info.symbol);
+    let new_key = symbol_key(&symbol);
+
+    if let Some(existing) = STOCK_SYMBOLS.may_load(deps.storage, &new_key)? {
+        if existing != id {
+            return Err(ContractError::DuplicateSymbol { symbol });
+        }
+    }
+
+    let old_symbol = stock_info.symbol.clone();
+    stock_info.symbol = symbol.clone();
+    STOCK_SYMBOLS.remove(deps.storage, &old_key);
+    STOCK_SYMBOLS.save(deps.storage, &new_key, &id)?;
+    STOCKS.save(deps.storage, id, &stock_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_stock_symbol")
+        .add_attribute("id", id.to_string())
+        .add_attribute("old_symbol", old_symbol)
+        .add_attribute("symbol", symbol)
+    )
+}
+
+fn marketing_token_address(storage: &dyn Storage, token: &MarketingToken) -> StdResult<Addr> {
+    match token {
+        MarketingToken::Pusd => Ok(CONFIG.load(storage)?.pusd_address),
+        MarketingToken::Stock { id } => Ok(STOCKS.load(storage, *id)?.stock_address),
+    }
+}
+
+pub fn execute_update_token_marketing(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: MarketingToken,
+    project: Option<String>,
+    description: Option<String>,
+    marketing: Option<String>
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+    check_bootstrapped(deps.storage)?;
+
+    if let Some(marketing) = marketing.as_ref() {
+        // stock tokens keep the manager as marketing address for metadata pushes
+        if let MarketingToken::Stock { .. } = token {
+            return Err(ContractError::StockMarketingFixed {});
+        }
+        deps.api.addr_validate(marketing)?;
+    }
+    let token_address = marketing_token_address(deps.storage, &token)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::UpdateMarketing {
+                project,
+                description,
+                marketing
+            })?,
+        }))
+        .add_attribute("action", "update_token_marketing")
+        .add_attribute("token", token_address)
+    )
+}
+
+pub fn execute_upload_token_logo(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: MarketingToken,
+    logo: Logo
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+    check_bootstrapped(deps.storage)?;
+
+    manager::verify_logo(&logo)?;
+    let token_address = marketing_token_address(deps.storage, &token)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::UploadLogo(logo))?,
+        }))
+        .add_attribute("action", "upload_token_logo")
+        .add_attribute("token", token_address)
+    )
+}
+
+/// True when the manager is the marketing address of the token, the only case where
+/// it can push UpdateMarketing. Tokens listed before that keep the owner as marketing.
+fn is_marketing_address(deps: Deps, env: &Env, token_address: &Addr) -> StdResult<bool> {
+    let marketing_info: MarketingInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: token_address.clone().into(),
+        msg: to_binary(&Cw20QueryMsg::MarketingInfo {})?,
+    }))?;
+    Ok(marketing_info.marketing.map_or(false, |marketing| marketing == env.contract.address))
+}
+
+pub fn execute_update_stock_metadata(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u32,
+    metadata: StockMetadata
+) -> Result<Response, ContractError> {
+    check_role(deps.storage, Role::Lister, info.sender.clone())?;
+    metadata.validate()?;
+
+    let mut stock_info = STOCKS.load(deps.storage, id)?;
+    let description = metadata.marketing_description().unwrap_or_default();
+    stock_info.metadata = metadata;
+    STOCKS.save(deps.storage, id, &stock_info)?;
+
+    let mut response = Response::new();
+    // otherwise the metadata is only stored, the marketing address updates the token itself
+    let pushed = is_marketing_address(deps.as_ref(), &env, &stock_info.stock_address)?;
+    if pushed {
+        response = response.add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: stock_info.stock_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::UpdateMarketing {
+                project: None,
+                description: Some(description),
+                marketing: None
+            })?,
+        }));
+    }
+
+    Ok(response
+        .add_attribute("action", "update_stock_metadata")
+        .add_attribute("id", id.to_string())
+        .add_attribute("pushed", pushed.to_string())
+    )
+}
+
+pub fn execute_update_stock_status(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    id: u32,
+    status: StockStatus
+) -> Result<Response, ContractError> {
+    check_role(deps.storage, Role::Pauser, info.sender.clone())?;
+
+    // Delisting goes through DelistStock and cannot be undone
+    let mut stock_info = STOCKS.load(deps.storage, id)?;
+    if status == StockStatus::Delisted || stock_info.status == StockStatus::Delisted {
+        return Err(ContractError::StockDelisted { id });
+    }
+    if BREAKER_TRIPS.has(deps.storage, id) {
+        return Err(ContractError::CircuitBreakerTripped { id });
+    }
+    stock_info.status = status;
+    STOCKS.save(deps.storage, id, &stock_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_stock_status")
+        .add_attribute("id", id.to_string())
+        .add_attribute("status", status.as_str())
+    )
+}
+
+pub fn execute_receive(
+    deps: DepsMut, 
+    env: Env,
+    info: MessageInfo, 
+    wrapper: Cw20ReceiveMsg
+) -> Result<Response, ContractError> {
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
+    let user_addr = &deps.api.addr_validate(&wrapper.sender)?;
+    
+    
+    if wrapper.amount == Uint128::zero() {
+        return Err(ContractError::InvalidInput {});
+    }
+
+    match msg {
+        ReceiveMsg::FundStkn {} => {
+            manager::check_enabled(deps.querier, env.contract.address.clone())?;
+
+            // Update Amount
+            if info.sender != cfg.stkn_address {
+                return Err(ContractError::UnacceptableToken {});
+            }
+
+            STKN_FUNDERS.update(deps.storage, user_addr, |funded| -> StdResult<_> {
+                Ok(funded.unwrap_or_default() + wrapper.amount)
+            })?;
+            let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+            record_reserve(deps.storage, &env, "fund", user_addr, wrapper.amount, balance)?;
+            
+            return Ok(Response::new()
+                .add_attributes(vec![
+                    attr("action", "fund_stkn"),
+                    attr("address", user_addr),
+                    attr("amount", wrapper.amount)
+                ])); 
+            
+        },
+        ReceiveMsg::Swap { min_output, max_price, deadline, recipient } => {
+            manager::check_enabled(deps.querier, env.contract.address.clone())?;
+            check_not_settled(deps.storage)?;
+            check_bootstrapped(deps.storage)?;
+
+            if let Some(deadline) = deadline {
+                if deadline.is_expired(&env.block) {
+                    return Err(ContractError::SwapExpired {});
+                }
+            }
+            let recipient = match recipient {
+                Some(recipient) => deps.api.addr_validate(&recipient)?,
+                None => user_addr.clone()
+            };
+
+            let swap = simulate_swap(deps.as_ref(), &env, &cfg, info.sender.clone(), wrapper.amount)?;
+
+            if let Some(max_price) = max_price {
+                if !swap.offer_stkn && swap.price > max_price {
+                    return Err(ContractError::PriceTooHigh { price: swap.price, max_price });
+                }
+            }
+            if let Some(min_output) = min_output {
+                if swap.return_amount < min_output {
+                    return Err(ContractError::SlippageExceeded { output: swap.return_amount, min_output });
+                }
+            }
+            if swap.return_amount.is_zero() {
+                return Err(ContractError::InvalidInput {});
+            }
+
+            let mut messages:Vec<CosmosMsg> = vec![];
+            if swap.offer_stkn {
+                ACCRUED_FEES.update(deps.storage, |fees| -> StdResult<_> { Ok(fees + swap.fee_amount) })?;
+                let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+                record_reserve(deps.storage, &env, "swap_in", user_addr, wrapper.amount, balance)?;
+
+                // Store stkn and mint according pusd to recipient
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: cfg.pusd_address.clone().into(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Mint {
+                        recipient: recipient.clone().into(),
+                        amount: swap.return_amount
+                    })?,
+                }));
+                return Ok(Response::new()
+                    .add_messages(messages)
+                    .add_attributes(vec![
+                        attr("action", "buy_pusd"),
+                        attr("address", user_addr),
+                        attr("recipient", recipient),
+                        attr("price", swap.price),
+                        attr("amount", swap.return_amount),
+                        attr("fee_amount", swap.fee_amount)
+                    ]));
+
+            } else {
+                // Burn received pusd and send stkn
+                if swap.return_amount + swap.fee_amount > get_available_stkn(deps.as_ref(), &env, &cfg)? {
+                    return Err(ContractError::InsufficientStkn {});
+                }
+                ACCRUED_FEES.update(deps.storage, |fees| -> StdResult<_> { Ok(fees + swap.fee_amount) })?;
+                let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+                record_reserve(deps.storage, &env, "swap_out", &recipient, swap.return_amount, balance - swap.return_amount)?;
+
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: cfg.pusd_address.clone().into(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: wrapper.amount
+                    })?,
+                }));
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: cfg.stkn_address.clone().into(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: recipient.clone().into(),
+                        amount: swap.return_amount
+                    })?,
+                }));
+                return Ok(Response::new()
+                    .add_messages(messages)
+                    .add_attributes(vec![
+                        attr("action", "buy_stkn"),
+                        attr("address", user_addr),
+                        attr("recipient", recipient),
+                        attr("price", swap.price),
+                        attr("amount", swap.return_amount),
+                        attr("fee_amount", swap.fee_amount)
+                ]));
+            }
+        },
+        ReceiveMsg::RedeemPusd { recipient } => {
+            let settlement = SETTLEMENT.may_load(deps.storage)?.ok_or(ContractError::NotSettled {})?;
+            if !settlement.complete {
+                return Err(ContractError::SettlementIncomplete {});
+            }
+            if info.sender != cfg.pusd_address {
+                return Err(ContractError::UnacceptableToken {});
+            }
+            let recipient = match recipient {
+                Some(recipient) => deps.api.addr_validate(&recipient)?,
+                None => user_addr.clone()
+            };
+
+            let stkn_amount = wrapper.amount * settlement.redemption_rate;
+            if stkn_amount > get_available_stkn(deps.as_ref(), &env, &cfg)? {
+                return Err(ContractError::InsufficientStkn {});
+            }
+            let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+            record_reserve(deps.storage, &env, "redeem", &recipient, stkn_amount, balance - stkn_amount)?;
+
+            return Ok(Response::new()
+                .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: cfg.pusd_address.clone().into(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: wrapper.amount
+                    })?,
+                }))
+                .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: cfg.stkn_address.clone().into(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: recipient.clone().into(),
+                        amount: stkn_amount
+                    })?,
+                }))
+                .add_attributes(vec![
+                    attr("action", "redeem_pusd"),
+                    attr("address", user_addr),
+                    attr("recipient", recipient),
+                    attr("pusd_amount", wrapper.amount),
+                    attr("amount", stkn_amount)
+                ]));
+        },
+        ReceiveMsg::SettleStock { recipient } => {
+            let recipient = match recipient {
+                Some(recipient) => deps.api.addr_validate(&recipient)?,
+                None => user_addr.clone()
+            };
+            return execute_settle_stock(deps, info.sender.clone(), user_addr.clone(), recipient, wrapper.amount);
+        },
+        ReceiveMsg::RedeemDelisted { recipient } => {
+            let recipient = match recipient {
+                Some(recipient) => deps.api.addr_validate(&recipient)?,
+                None => user_addr.clone()
+            };
+            return execute_redeem_delisted(deps, env, info.sender.clone(), user_addr.clone(), recipient, wrapper.amount);
+        },
+        ReceiveMsg::Burn { id } => {
+            check_stock_subcontract(deps.storage, id, user_addr.clone())?;
+            let stock_info = STOCKS.load(deps.storage, id)?;
+            let kind = if info.sender == cfg.pusd_address {
+                MintKind::Pusd
+            } else if info.sender == stock_info.stock_address {
+                MintKind::Stock
+            } else {
+                return Err(ContractError::UnacceptableToken {});
+            };
+            record_burn(deps.storage, id, kind, wrapper.amount)?;
+
+            return Ok(Response::new()
+                .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: info.sender.clone().into(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: wrapper.amount
+                    })?,
+                }))
+                .add_attributes(vec![
+                    attr("action", "burn"),
+                    attr("id", id.to_string()),
+                    attr("kind", kind.as_str()),
+                    attr("address", user_addr),
+                    attr("amount", wrapper.amount)
+                ]));
+        }
+    }
+    
+}
+
+pub struct SwapSimulation {
+    pub offer_stkn: bool,
+    pub price: Uint128,
+    pub offer_amount: Uint128,
+    pub return_amount: Uint128,
+    /// Fees are always kept in STKN
+    pub fee_amount: Uint128
+}
+
+fn multiply_ratio_ceil(amount: Uint128, numerator: u128, denominator: u128) -> Uint128 {
+    let floor = amount.multiply_ratio(numerator, denominator);
+    if amount.full_mul(numerator) % Uint256::from(denominator) != Uint256::zero() {
+        floor + Uint128::from(1u128)
+    } else {
+        floor
+    }
+}
+
+/// Converts offer_amount of offer_token (STKN or PUSD) into the other token at the current swap price.
+pub fn simulate_swap(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config,
+    offer_token: Addr,
+    offer_amount: Uint128
+) -> Result<SwapSimulation, ContractError> {
+    let offer_stkn = if offer_token == cfg.stkn_address {
+        true
+    } else if offer_token == cfg.pusd_address {
+        false
+    } else {
+        return Err(ContractError::UnacceptableToken {});
+    };
+    let price = get_swap_price(deps, env, cfg)?.price;
+
+    let (return_amount, fee_amount) = if offer_stkn {
+        let fee_amount = offer_amount.multiply_ratio(cfg.buy_pusd_fee_bps, FEE_DENOMINATOR);
+        ((offer_amount - fee_amount).multiply_ratio(price, NORMAL_DECIMAL), fee_amount)
+    } else {
+        let stkn_amount = offer_amount.multiply_ratio(NORMAL_DECIMAL, price);
+        let fee_amount = stkn_amount.multiply_ratio(cfg.sell_pusd_fee_bps, FEE_DENOMINATOR);
+        (stkn_amount - fee_amount, fee_amount)
+    };
+
+    Ok(SwapSimulation { offer_stkn, price, offer_amount, return_amount, fee_amount })
+}
+
+/// Smallest amount of offer token that returns at least ask_amount of ask_token.
+pub fn reverse_simulate_swap(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config,
+    ask_token: Addr,
+    ask_amount: Uint128
+) -> Result<SwapSimulation, ContractError> {
+    let offer_stkn = if ask_token == cfg.pusd_address {
+        true
+    } else if ask_token == cfg.stkn_address {
+        false
+    } else {
+        return Err(ContractError::UnacceptableToken {});
+    };
+    let price = get_swap_price(deps, env, cfg)?.price;
+
+    let (offer_amount, fee_amount) = if offer_stkn {
+        let net_stkn = multiply_ratio_ceil(ask_amount, NORMAL_DECIMAL, price.u128());
+        let offer_amount = multiply_ratio_ceil(net_stkn, FEE_DENOMINATOR as u128, (FEE_DENOMINATOR - cfg.buy_pusd_fee_bps) as u128);
+        (offer_amount, offer_amount.multiply_ratio(cfg.buy_pusd_fee_bps, FEE_DENOMINATOR))
+    } else {
+        let gross_stkn = multiply_ratio_ceil(ask_amount, FEE_DENOMINATOR as u128, (FEE_DENOMINATOR - cfg.sell_pusd_fee_bps) as u128);
+        let offer_amount = multiply_ratio_ceil(gross_stkn, price.u128(), NORMAL_DECIMAL);
+        (offer_amount, gross_stkn.multiply_ratio(cfg.sell_pusd_fee_bps, FEE_DENOMINATOR))
+    };
+
+    Ok(SwapSimulation { offer_stkn, price, offer_amount, return_amount: ask_amount, fee_amount })
+}
+
+pub fn check_stock_subcontract(
+    storage: &dyn Storage,
+    id: u32,
+    address: Addr
+) -> Result<Response, ContractError> {
+    if is_stock_subcontract(storage, id, &address)? {
+        Ok(Response::new().add_attribute("action", "check_stock_contracts"))
+    } else {
+        Err(ContractError::Unauthorized {})
+    }
+}
+
+/// The owner and the protocol contracts act for every stock, subcontracts only for their own stock
+pub fn is_stock_subcontract(
+    storage: &dyn Storage,
+    id: u32,
+    address: &Addr
+) -> StdResult<bool> {
+    if CONFIG.load(storage)?.owner.as_ref() == Some(address) {
+        return Ok(true);
+    }
+    Ok(match ADDRESS_ROLES.may_load(storage, address)? {
+        Some(address_role) => address_role.stock_id.map_or(true, |stock_id| stock_id == id),
+        None => false
+    })
+}
+
+/// Stocks that are not registered (e.g. ids used by the staking contract) are not restricted.
+pub fn check_stock_status(
+    storage: &dyn Storage,
+    id: u32,
+    allowed: &[StockStatus]
+) -> Result<bool, ContractError> {
+    if let Some(stock_info) = STOCKS.may_load(storage, id)? {
+        if !allowed.contains(&stock_info.status) {
+            return Err(ContractError::StockNotActive { id, status: stock_info.status.as_str().to_string() });
+        }
+    }
+    Ok(true)
+}
+
+/// Usage with the buckets older than the window dropped
+fn load_mint_usage(
+    storage: &dyn Storage,
+    env: &Env,
+    id: u32,
+    kind: MintKind
+) -> StdResult<(Option<MintLimit>, MintUsage)> {
+    let limit = MINT_LIMITS.may_load(storage, (id, kind.as_str()))?;
+    let mut usage = MINT_USAGE.may_load(storage, (id, kind.as_str()))?.unwrap_or_default();
+    let window = limit.as_ref().map_or(0, |l| l.window);
+    usage.window.prune(env.block.time.seconds(), window);
+    Ok((limit, usage))
+}
+
+pub fn record_mint(
+    storage: &mut dyn Storage,
+    env: &Env,
+    id: u32,
+    kind: MintKind,
+    caller: &Addr,
+    amount: Uint128
+) -> Result<(), ContractError> {
+    let (limit, mut usage) = load_mint_usage(storage, env, id, kind)?;
+    let window = limit.as_ref().map_or(0, |l| l.window);
+    usage.total_minted = usage.total_minted.checked_add(amount).map_err(StdError::overflow)?;
+    usage.outstanding = usage.outstanding.checked_add(amount).map_err(StdError::overflow)?;
+    usage.window.add(env.block.time.seconds(), window, amount)?;
+
+    if let Some(limit) = limit {
+        if limit.ceiling.map_or(false, |ceiling| usage.outstanding > ceiling) {
+            return Err(ContractError::MintCeilingExceeded { id });
+        }
+        if limit.window_limit.map_or(Ok(false), |window_limit| -> StdResult<_> {
+            Ok(usage.window.total()? > window_limit)
+        })? {
+            return Err(ContractError::MintRateLimitExceeded { id });
+        }
+    }
+    MINT_USAGE.save(storage, (id, kind.as_str()), &usage)?;
+    CALLER_MINTED.update(storage, (id, kind.as_str(), caller), |minted| -> StdResult<_> {
+        Ok(minted.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+/// Lowers the outstanding amount, burns never refill the rate limit window
+pub fn record_burn(
+    storage: &mut dyn Storage,
+    id: u32,
+    kind: MintKind,
+    amount: Uint128
+) -> StdResult<()> {
+    MINT_USAGE.update(storage, (id, kind.as_str()), |usage| -> StdResult<_> {
+        let mut usage = usage.unwrap_or_default();
+        usage.outstanding = usage.outstanding.saturating_sub(amount);
+        Ok(usage)
+    })?;
+    Ok(())
+}
+
+pub fn execute_update_circuit_breaker(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    id: u32,
+    breaker: Option<CircuitBreaker>
+) -> Result<Response, ContractError> {
+    STOCKS.load(deps.storage, id)?;
+
+    match breaker {
+        Some(breaker) => {
+            if breaker.window == 0 {
+                return Err(ContractError::InvalidInput {});
+            }
+            CIRCUIT_BREAKERS.save(deps.storage, id, &breaker)?;
+        },
+        None => CIRCUIT_BREAKERS.remove(deps.storage, id)
+    }
+    BREAKER_WINDOWS.remove(deps.storage, id);
+
+    Ok(Response::new()
+        .add_attribute("action", "update_circuit_breaker")
+        .add_attribute("id", id.to_string())
+    )
+}
+
+/// Stock denominated limits and mint accounting follow a split, PUSD amounts stay as they are
+fn rescale_stock_amounts(
+    storage: &mut dyn Storage,
+    id: u32,
+    numerator: u64,
+    denominator: u64
+) -> StdResult<()> {
+    let scale = |amount: Uint128| amount.multiply_ratio(numerator, denominator);
+    let kind = MintKind::Stock.as_str();
+
+    if let Some(mut limit) = MINT_LIMITS.may_load(storage, (id, kind))? {
+        limit.ceiling = limit.ceiling.map(scale);
+        limit.window_limit = limit.window_limit.map(scale);
+        MINT_LIMITS.save(storage, (id, kind), &limit)?;
+    }
+    if let Some(mut usage) = MINT_USAGE.may_load(storage, (id, kind))? {
+        usage.total_minted = scale(usage.total_minted);
+        usage.outstanding = scale(usage.outstanding);
+        for (_, amount) in usage.window.buckets.iter_mut() {
+            *amount = scale(*amount);
+        }
+        MINT_USAGE.save(storage, (id, kind), &usage)?;
+    }
+    let callers: Vec<(Addr, Uint128)> = CALLER_MINTED
+        .prefix((id, kind))
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for (caller, minted) in callers {
+        CALLER_MINTED.save(storage, (id, kind, &caller), &scale(minted))?;
+    }
+    if let Some(mut breaker) = CIRCUIT_BREAKERS.may_load(storage, id)? {
+        breaker.max_stock_minted = breaker.max_stock_minted.map(scale);
+        CIRCUIT_BREAKERS.save(storage, id, &breaker)?;
+    }
+    Ok(())
+}
+
+/// Positions still open in the shorting and trading contracts of the stock
+fn open_positions(deps: Deps, stock_info: &StockInfo) -> StdResult<u64> {
+    let mut count = 0u64;
+    for contract_addr in [&stock_info.shorting_address, &stock_info.trading_address] {
+        let response: OpenPositionsResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: contract_addr.clone().into(),
+            msg: to_binary(&SubcontractQueryMsg::OpenPositions {})?,
+        }))?;
+        count = count.saturating_add(response.count);
+    }
+    Ok(count)
+}
+
+pub fn execute_corporate_action(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    action: CorporateAction
+) -> Result<Response, ContractError> {
+    check_not_settled(deps.storage)?;
+
+    let id = action.stock_id();
+    let stock_info = STOCKS.load(deps.storage, id)?;
+    if stock_info.status == StockStatus::Delisted {
+        return Err(ContractError::StockDelisted { id });
+    }
+
+    let messages: Vec<CosmosMsg> = match action {
+        CorporateAction::Split { numerator, denominator, .. } => {
+            if numerator == 0 || denominator == 0 || numerator == denominator {
+                return Err(ContractError::InvalidInput {});
+            }
+            // shorting and trading positions are not rescaled, they have to be closed while paused
+            if stock_info.status != StockStatus::Paused {
+                return Err(ContractError::StockNotPaused { id });
+            }
+            let count = open_positions(deps.as_ref(), &stock_info)?;
+            if count > 0 {
+                return Err(ContractError::OpenPositions { id, count });
+            }
+            // stock volumes and prices of the running window are in pre-split units
+            BREAKER_WINDOWS.remove(deps.storage, id);
+            rescale_stock_amounts(deps.storage, id, numerator, denominator)?;
+
+            vec![
+                stock_info.stock_address.clone(),
+                stock_info.pool_address.clone(),
+            ]
+                .into_iter()
+                .map(|contract_addr| -> StdResult<CosmosMsg> {
+                    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: contract_addr.into(),
+                        funds: vec![],
+                        msg: to_binary(&SubcontractExecuteMsg::Split { numerator, denominator })?,
+                    }))
+                })
+                .collect::<StdResult<_>>()?
+        }
+    };
+
+    let action_id = CORPORATE_ACTION_COUNT.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+    CORPORATE_ACTIONS.save(deps.storage, action_id, &CorporateActionRecord {
+        id: action_id,
+        action,
+        height: env.block.height,
+        time: env.block.time.seconds()
+    })?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "corporate_action")
+        .add_attribute("action_id", action_id.to_string())
+        .add_attribute("id", id.to_string())
+    )
+}
+
+pub fn execute_resume(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    id: u32
+) -> Result<Response, ContractError> {
+    check_role(deps.storage, Role::Guardian, info.sender.clone())?;
+
+    let trip = BREAKER_TRIPS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::CircuitBreakerNotTripped { id })?;
+    BREAKER_TRIPS.remove(deps.storage, id);
+    BREAKER_WINDOWS.remove(deps.storage, id);
+
+    // a stock delisted while tripped stays delisted
+    let stock_info = STOCKS.update(deps.storage, id, |exists| -> StdResult<_> {
+        let mut stock_info = exists.ok_or_else(|| StdError::not_found("StockInfo"))?;
+        if stock_info.status != StockStatus::Delisted {
+            stock_info.status = trip.previous_status;
+        }
+        Ok(stock_info)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "resume")
+        .add_attribute("id", id.to_string())
+        .add_attribute("status", stock_info.status.as_str())
+    )
+}
+
+/// Adds a mint, if any, to the rolling circuit breaker window of the stock and samples its price. Returns the
+/// threshold passed, if any, with the PUSD and stock volumes of the window, which is only saved when none was.
+fn sample_circuit_breaker(
+    deps: DepsMut,
+    env: &Env,
+    id: u32,
+    breaker: &CircuitBreaker,
+    stock_address: &Addr,
+    mint: Option<(MintKind, Uint128)>
+) -> Result<Option<(&'static str, Uint128, Uint128)>, ContractError> {
+    let now = env.block.time.seconds();
+    let mut window = BREAKER_WINDOWS.may_load(deps.storage, id)?.unwrap_or_default();
+    window.pusd_minted.prune(now, breaker.window);
+    window.stock_minted.prune(now, breaker.window);
+    window.prices.prune(now, breaker.window);
+    match mint {
+        Some((MintKind::Pusd, amount)) => window.pusd_minted.add(now, breaker.window, amount)?,
+        Some((MintKind::Stock, amount)) => window.stock_minted.add(now, breaker.window, amount)?,
+        None => {}
+    }
+
+    let price_change_bps = match breaker.max_price_change_bps {
+        Some(_) => {
+            let stock_response: StockConfigResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: stock_address.clone().into(),
+                msg: to_binary(&StockQueryMsg::Config {})?,
+            }))?;
+            let change = price_change_bps(&window.prices, stock_response.price);
+            window.prices.add(now, breaker.window, stock_response.price);
+            Some(change)
+        },
+        None => None
+    };
+    let pusd_minted = window.pusd_minted.total()?;
+    let stock_minted = window.stock_minted.total()?;
+    let reason = if breaker.max_pusd_minted.map_or(false, |max| pusd_minted > max) {
+        "pusd_volume"
+    } else if breaker.max_stock_minted.map_or(false, |max| stock_minted > max) {
+        "stock_volume"
+    } else if breaker.max_price_change_bps.zip(price_change_bps).map_or(false, |(max, change)| change > max) {
+        "price_change"
+    } else {
+        BREAKER_WINDOWS.save(deps.storage, id, &window)?;
+        return Ok(None);
+    };
+    // a rejected mint is not counted, Resume starts a new window after a trip
+    Ok(Some((reason, pusd_minted, stock_minted)))
+}
+
+/// Rejects a mint that would pass a circuit breaker threshold, before any mint message is built. The mint is
+/// counted in the window otherwise.
+fn check_circuit_breaker(
+    deps: DepsMut,
+    env: &Env,
+    id: u32,
+    kind: MintKind,
+    amount: Uint128
+) -> Result<(), ContractError> {
+    let breaker = match CIRCUIT_BREAKERS.may_load(deps.storage, id)? {
+        Some(breaker) => breaker,
+        None => return Ok(())
+    };
+    if BREAKER_TRIPS.has(deps.storage, id) {
+        return Err(ContractError::CircuitBreakerTripped { id });
+    }
+    let stock_info = STOCKS.load(deps.storage, id)?;
+
+    match sample_circuit_breaker(deps, env, id, &breaker, &stock_info.stock_address, Some((kind, amount)))? {
+        Some((reason, _, _)) => Err(ContractError::CircuitBreakerLimit { id, reason: reason.to_string() }),
+        None => Ok(())
+    }
+}
+
+/// Samples the stock price into its circuit breaker window and pauses the stock when a threshold is passed.
+/// Mints never get here, the ones passing a threshold are rejected by check_circuit_breaker.
+fn record_circuit_breaker(
+    mut deps: DepsMut,
+    env: &Env,
+    id: u32
+) -> Result<Option<Event>, ContractError> {
+    let breaker = match CIRCUIT_BREAKERS.may_load(deps.storage, id)? {
+        Some(breaker) => breaker,
+        None => return Ok(None)
+    };
+    let stock_info = match STOCKS.may_load(deps.storage, id)? {
+        Some(stock_info) => stock_info,
+        None => return Ok(None)
+    };
+    // already tripped, Resume restores the status recorded then
+    if BREAKER_TRIPS.has(deps.storage, id) {
+        return Ok(None);
+    }
+
+    let (reason, pusd_minted, stock_minted) = match sample_circuit_breaker(deps.branch(), env, id, &breaker, &stock_info.stock_address, None)? {
+        Some(trip) => trip,
+        None => return Ok(None)
+    };
+
+    BREAKER_TRIPS.save(deps.storage, id, &BreakerTrip {
+        time: env.block.time.seconds(),
+        reason: reason.to_string(),
+        previous_status: stock_info.status
+    })?;
+    // a delisted stock stays delisted, the recorded trip holds its redemptions
+    STOCKS.update(deps.storage, id, |exists| -> StdResult<_> {
+        let mut stock_info = exists.ok_or_else(|| StdError::not_found("StockInfo"))?;
+        if stock_info.status != StockStatus::Delisted {
+            stock_info.status = StockStatus::Paused;
+        }
+        Ok(stock_info)
+    })?;
+
+    Ok(Some(Event::new("circuit_breaker_tripped")
+        .add_attribute("id", id.to_string())
+        .add_attribute("reason", reason)
+        .add_attribute("pusd_minted", pusd_minted)
+        .add_attribute("stock_minted", stock_minted)
+    ))
+}
+
+/// Largest move of price against the lowest and highest prices of the window, in basis points
+fn price_change_bps(prices: &PriceWindow, price: Uint128) -> u64 {
+    prices.buckets
+        .iter()
+        .map(|(_, low, high)| {
+            let rise = if price > *low && !low.is_zero() { (price - *low).multiply_ratio(FEE_DENOMINATOR, *low) } else { Uint128::zero() };
+            let fall = if *high > price { (*high - price).multiply_ratio(FEE_DENOMINATOR, *high) } else { Uint128::zero() };
+            u64::try_from(std::cmp::max(rise, fall).u128()).unwrap_or(u64::MAX)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Samples the stock price into its circuit breaker window, so a price move trips it without a mint
+pub fn execute_check_circuit_breaker(
+    deps: DepsMut,
+    env: Env,
+    id: u32
+) -> Result<Response, ContractError> {
+    STOCKS.load(deps.storage, id)?;
+    let trip = record_circuit_breaker(deps, &env, id)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "check_circuit_breaker")
+        .add_attribute("id", id.to_string())
+        .add_attribute("tripped", trip.is_some().to_string())
+        .add_events(trip)
+    )
+}
+
+pub fn check_not_settled(storage: &dyn Storage) -> Result<bool, ContractError> {
+    if SETTLEMENT.may_load(storage)?.is_some() {
+        return Err(ContractError::Settled {});
+    }
+    Ok(true)
+}
+
+pub fn execute_global_settlement(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo
+) -> Result<Response, ContractError> {
+    // authorize owner
+    manager::check_owner(deps.querier, env.contract.address.clone(), info.sender.clone())?;
+    check_not_settled(deps.storage)?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+    // Settlement must not fail on a stale feed, the last known price is frozen instead
+    let stkn_price = match get_swap_price(deps.as_ref(), &env, &cfg) {
+        Ok(price) => price,
+        Err(_) => get_last_known_price(deps.as_ref(), &cfg)?
+    };
+    let pusd_supply = get_pusd_supply(deps.as_ref(), &cfg)?;
+    let stkn_reserve = get_available_stkn(deps.as_ref(), &env, &cfg)?;
+
+    SETTLEMENT.save(deps.storage, &Settlement {
+        time: env.block.time.seconds(),
+        stkn_price: stkn_price.price,
+        pusd_supply,
+        stkn_reserve,
+        total_claims: pusd_supply,
+        last_stock: None,
+        complete: false,
+        redemption_rate: Decimal::zero()
+    })?;
+    let settlement = snapshot_stocks(deps, DEFAULT_LIMIT as usize)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "global_settlement")
+        .add_attribute("stkn_price", stkn_price.price)
+        .add_attribute("price_source", stkn_price.source)
+        .add_attribute("complete", settlement.complete.to_string())
+    )
+}
+
+pub fn execute_settle_stocks(
+    deps: DepsMut,
+    _env: Env,
+    limit: Option<u32>
+) -> Result<Response, ContractError> {
+    let settlement = SETTLEMENT.may_load(deps.storage)?.ok_or(ContractError::NotSettled {})?;
+    if settlement.complete {
+        return Err(ContractError::SettlementComplete {});
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let settlement = snapshot_stocks(deps, limit)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "settle_stocks")
+        .add_attribute("complete", settlement.complete.to_string())
+        .add_attribute("total_claims", settlement.total_claims)
+        .add_attribute("redemption_rate", settlement.redemption_rate.to_string())
+    )
+}
+
+/// Freezes price and supply of the next limit stocks, and fixes the redemption rate after the last one.
+/// Minting is stopped during settlement, so the supplies cannot change between batches
+fn snapshot_stocks(deps: DepsMut, limit: usize) -> Result<Settlement, ContractError> {
+    let mut settlement = SETTLEMENT.load(deps.storage)?;
+    let stocks = STOCKS
+        .range(deps.storage, settlement.last_stock.map(Bound::exclusive), None, Order::Ascending)
+        .take(limit)
+        .map(map_stock)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // Every stock token is a claim on PUSD at its frozen price
+    for stock_info in stocks.iter() {
+        // Delisted stocks keep the price frozen at delisting
+        let price = match DELISTINGS.may_load(deps.storage, stock_info.id)? {
+            Some(delisting) => delisting.price,
+            None => {
+                let stock_response: StockConfigResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: stock_info.stock_address.clone().into(),
+                    msg: to_binary(&StockQueryMsg::Config {})?,
+                }))?;
+                stock_response.price
+            }
+        };
+        let token_info: TokenInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: stock_info.stock_address.clone().into(),
+            msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+        }))?;
+        SETTLED_STOCKS.save(deps.storage, stock_info.id, &SettledStock {
+            price,
+            supply: token_info.total_supply,
+            settled: Uint128::zero()
+        })?;
+        settlement.total_claims = settlement.total_claims
+            .checked_add(token_info.total_supply.multiply_ratio(price, NORMAL_DECIMAL))
+            .map_err(StdError::overflow)?;
+        settlement.last_stock = Some(stock_info.id);
+    }
+
+    if stocks.len() < limit {
+        // Pro rata share of the reserve, never more than the frozen price pays
+        let full_rate = Decimal::from_ratio(NORMAL_DECIMAL, settlement.stkn_price);
+        settlement.redemption_rate = if settlement.total_claims.is_zero() {
+            full_rate
+        } else {
+            std::cmp::min(full_rate, Decimal::from_ratio(settlement.stkn_reserve, settlement.total_claims))
+        };
+        settlement.complete = true;
+    }
+    SETTLEMENT.save(deps.storage, &settlement)?;
+    Ok(settlement)
+}
+
+/// Stock tokens received from a holder, burned and paid in PUSD up to the snapshotted supply
+fn execute_settle_stock(
+    deps: DepsMut,
+    token: Addr,
+    sender: Addr,
+    recipient: Addr,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+    let settlement = SETTLEMENT.may_load(deps.storage)?.ok_or(ContractError::NotSettled {})?;
+    if !settlement.complete {
+        return Err(ContractError::SettlementIncomplete {});
+    }
+    let id = stock_token_id(deps.storage, &token)?;
+
+    let mut settled = SETTLED_STOCKS.load(deps.storage, id)?;
+    settled.settled = settled.settled.checked_add(amount).map_err(StdError::overflow)?;
+    if settled.settled > settled.supply {
+        return Err(ContractError::RedemptionCapExceeded { id });
+    }
+    SETTLED_STOCKS.save(deps.storage, id, &settled)?;
+    record_burn(deps.storage, id, MintKind::Stock, amount)?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let pusd_amount = amount.multiply_ratio(settled.price, NORMAL_DECIMAL);
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount
+            })?,
+        }))
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cfg.pusd_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: recipient.clone().into(),
+                amount: pusd_amount
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "settle_stock"),
+            attr("id", id.to_string()),
+            attr("address", sender),
+            attr("recipient", recipient),
+            attr("stock_amount", amount),
+            attr("amount", pusd_amount)
+        ])
+    )
+}
+
+/// Id of the stock whose token is address
+fn stock_token_id(storage: &dyn Storage, address: &Addr) -> Result<u32, ContractError> {
+    match ADDRESS_ROLES.may_load(storage, address)? {
+        Some(AddressRole { stock_id: Some(id), role: ContractRole::Stock }) => Ok(id),
+        _ => Err(ContractError::UnacceptableToken {})
+    }
+}
+
+/// Stock tokens of a delisted stock received from a holder, burned and paid in PUSD up to the supply at delisting.
+/// The PUSD is minted like MintPusd, against the limits, the collateral ratio and the circuit breaker.
+fn execute_redeem_delisted(
+    mut deps: DepsMut,
+    env: Env,
+    token: Addr,
+    sender: Addr,
+    recipient: Addr,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+    // holders can redeem while the manager is disabled
+    check_not_settled(deps.storage)?;
+    let id = stock_token_id(deps.storage, &token)?;
+
+    let mut delisting = DELISTINGS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::StockNotDelisted { id })?;
+    if env.block.time.seconds() > delisting.redemption_end {
+        return Err(ContractError::RedemptionClosed { id });
+    }
+    // a tripped breaker keeps the stock delisted, redemptions wait for Resume
+    if BREAKER_TRIPS.has(deps.storage, id) {
+        return Err(ContractError::StockNotActive { id, status: StockStatus::Paused.as_str().to_string() });
+    }
+    delisting.redeemed = delisting.redeemed.checked_add(amount).map_err(StdError::overflow)?;
+    if delisting.redeemed > delisting.supply {
+        return Err(ContractError::RedemptionCapExceeded { id });
+    }
+    DELISTINGS.save(deps.storage, id, &delisting)?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let pusd_amount = amount.multiply_ratio(delisting.price, NORMAL_DECIMAL);
+    check_circuit_breaker(deps.branch(), &env, id, MintKind::Pusd, pusd_amount)?;
+    record_burn(deps.storage, id, MintKind::Stock, amount)?;
+    record_mint(deps.storage, &env, id, MintKind::Pusd, &token, pusd_amount)?;
+    if let Some(min_ratio) = cfg.min_collateral_ratio {
+        let pusd_supply = get_pusd_supply(deps.as_ref(), &cfg)? + pusd_amount;
+        if get_collateral_ratio(deps.as_ref(), &env, &cfg, pusd_supply)?.map_or(false, |ratio| ratio < min_ratio) {
+            return Err(ContractError::CollateralRatioTooLow {});
+        }
+    }
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount
+            })?,
+        }))
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cfg.pusd_address.clone().into(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: recipient.clone().into(),
+                amount: pusd_amount
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "redeem_delisted"),
+            attr("id", id.to_string()),
+            attr("address", sender),
+            attr("recipient", recipient),
+            attr("stock_amount", amount),
+            attr("amount", pusd_amount)
+        ])
+    )
+}
+
+pub fn execute_mint_pusd(
+    mut deps: DepsMut,
+    env: Env,
+    caller: Addr,
+    id: u32,
+    recipient: Addr,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+
+    manager::check_enabled(deps.querier, env.contract.address.clone())?;
+    check_not_settled(deps.storage)?;
+    check_stock_subcontract(deps.storage, id, caller.clone())?;
+    check_stock_status(deps.storage, id, &[StockStatus::Active, StockStatus::ReduceOnly])?;
+    check_circuit_breaker(deps.branch(), &env, id, MintKind::Pusd, amount)?;
+    record_mint(deps.storage, &env, id, MintKind::Pusd, &caller, amount)?;
+    
+    let mut messages:Vec<CosmosMsg> = vec![];
+
+    let cfg = CONFIG.load(deps.storage)?;
+    if let Some(min_ratio) = cfg.min_collateral_ratio {
+        let pusd_supply = get_pusd_supply(deps.as_ref(), &cfg)? + amount;
+        if get_collateral_ratio(deps.as_ref(), &env, &cfg, pusd_supply)?.map_or(false, |ratio| ratio < min_ratio) {
+            return Err(ContractError::CollateralRatioTooLow {});
+        }
+    }
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: cfg.pusd_address.clone().into(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: recipient.clone().into(),
+            amount
+        })?,
+    }));
+
+    return Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "mint_pusd"),
+            attr("id", id.to_string()),
+            attr("address", recipient.clone()),
+            attr("amount", amount)
+    ]));
+}
+
+
+pub fn execute_mint_stock(
+    mut deps: DepsMut,
+    env: Env,
+    caller: Addr,
+    id: u32,
+    recipient: Addr,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+
+    manager::check_enabled(deps.querier, env.contract.address.clone())?;
+    check_not_settled(deps.storage)?;
+    check_stock_subcontract(deps.storage, id, caller.clone())?;
+    check_stock_status(deps.storage, id, &[StockStatus::Active])?;
+    check_circuit_breaker(deps.branch(), &env, id, MintKind::Stock, amount)?;
+    record_mint(deps.storage, &env, id, MintKind::Stock, &caller, amount)?;
+    
+    let stock_info = STOCKS.load(deps.storage, id)?;
+
+    let mut messages:Vec<CosmosMsg> = vec![];
+
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: stock_info.stock_address.clone().into(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: recipient.clone().into(),
+            amount
+        })?,
+    }));
+
+    return Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "mint_stock"),
+            attr("id", id.to_string()),
+            attr("address", recipient.clone()),
+            attr("amount", amount)
+    ]));
+}
+
+pub fn execute_transfer_stkn(
+    deps: DepsMut,
+    env: Env,
+    caller: Addr,
+    id: u32,
+    recipient: Addr,
+    amount: Uint128
+) -> Result<Response, ContractError> {
+
+    manager::check_enabled(deps.querier, env.contract.address.clone())?;
+    check_not_settled(deps.storage)?;
+    check_stock_subcontract(deps.storage, id, caller.clone())?;
+    check_stock_status(deps.storage, id, &[StockStatus::Active, StockStatus::ReduceOnly])?;
+
+    let cfg = CONFIG.load(deps.storage)?;
+
+    if get_available_stkn(deps.as_ref(), &env, &cfg)? < amount {
+        return Err(ContractError::NotEnoughStkn {});
+    }
+    
+    let balance = util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())?;
+    record_reserve(deps.storage, &env, "transfer", &recipient, amount, balance - amount)?;
+
+    let mut messages:Vec<CosmosMsg> = vec![];
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: cfg.stkn_address.clone().into(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.clone().into(),
+            amount
+        })?,
+    }));
+
+    
+    return Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "send_stkn"),
+            attr("id", id.to_string()),
+            attr("address", recipient.clone()),
+            attr("amount", amount)
+    ]));
+    
+
+}
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: ManagerQueryMsg) -> StdResult<Binary> {
+    match msg {
+        ManagerQueryMsg::Config {} 
+            => to_binary(&query_config(deps, env)?),
+        ManagerQueryMsg::Stock {id} 
+            => to_binary(&query_stock(deps, id)?),
+        ManagerQueryMsg::ListStocks { start_after, limit, status, symbol_prefix } 
+            => to_binary(&query_list_stocks(deps, start_after, limit, status, symbol_prefix)?),
+        ManagerQueryMsg::StockCount {} 
+            => to_binary(&query_stock_count(deps)?),
+        ManagerQueryMsg::StockBySymbol { symbol } 
+            => to_binary(&query_stock_by_symbol(deps, symbol)?),
+        ManagerQueryMsg::CheckStockSubcontract {id, address} 
+            => to_binary(&query_check_stock_subcontract(deps, id, address)?),
+        ManagerQueryMsg::SubcontractRole { address } 
+            => to_binary(&query_subcontract_role(deps, address)?),
+        ManagerQueryMsg::Delisting { id } 
+            => to_binary(&DelistingResponse { delisting: DELISTINGS.may_load(deps.storage, id)? }),
+        ManagerQueryMsg::ArchivedStocks { start_after, limit } 
+            => to_binary(&query_archived_stocks(deps, start_after, limit)?),
+        ManagerQueryMsg::CircuitBreaker { id } 
+            => to_binary(&CircuitBreakerResponse {
+                id,
+                breaker: CIRCUIT_BREAKERS.may_load(deps.storage, id)?,
+                window: BREAKER_WINDOWS.may_load(deps.storage, id)?,
+                trip: BREAKER_TRIPS.may_load(deps.storage, id)?
+            }),
+        ManagerQueryMsg::CorporateAction { action_id } 
+            => to_binary(&CORPORATE_ACTIONS.load(deps.storage, action_id)?),
+        ManagerQueryMsg::CorporateActions { id, start_after, limit } 
+            => to_binary(&query_corporate_actions(deps, id, start_after, limit)?),
+        ManagerQueryMsg::DueForSync { limit } 
+            => to_binary(&query_due_for_sync(deps, env, limit)?),
+        ManagerQueryMsg::TimelockDelays {} 
+            => to_binary(&query_timelock_delays(deps)?),
+        ManagerQueryMsg::Multisig {} 
+            => to_binary(&query_multisig(deps)?),
+        ManagerQueryMsg::Proposal { id } 
+            => to_binary(&query_proposal(deps, env, id)?),
+        ManagerQueryMsg::ListProposals { start_after, limit } 
+            => to_binary(&query_list_proposals(deps, env, start_after, limit)?),
+        ManagerQueryMsg::ProposalVote { id, address } 
+            => to_binary(&ProposalVoteResponse { yes: PROPOSAL_VOTES.may_load(deps.storage, (id, &address))? }),
+        ManagerQueryMsg::ScheduledOperation { id } 
+            => to_binary(&SCHEDULED_OPERATIONS.load(deps.storage, id)?),
+        ManagerQueryMsg::ScheduledOperations { start_after, limit } 
+            => to_binary(&query_scheduled_operations(deps, start_after, limit)?),
+        ManagerQueryMsg::BootstrapStatus {} 
+            => to_binary(&query_bootstrap_status(deps)?),
+        ManagerQueryMsg::PendingOwner {} 
+            => to_binary(&query_pending_owner(deps)?),
+        ManagerQueryMsg::Price {} 
+            => to_binary(&query_price(deps, env)?),
+        ManagerQueryMsg::Settlement {} 
+            => to_binary(&query_settlement(deps)?),
+        ManagerQueryMsg::SettledStock { id } 
+            => to_binary(&SETTLED_STOCKS.load(deps.storage, id)?),
+        ManagerQueryMsg::Solvency { start_after, limit } 
+            => to_binary(&query_solvency(deps, env, start_after, limit)?),
+        ManagerQueryMsg::StknFunded { address } 
+            => to_binary(&query_stkn_funded(deps, address)?),
+        ManagerQueryMsg::ReserveHistory { start_after, limit } 
+            => to_binary(&query_reserve_history(deps, start_after, limit)?),
+        ManagerQueryMsg::SimulateSwap { offer_token, offer_amount } 
+            => to_binary(&query_simulate_swap(deps, env, offer_token, offer_amount)?),
+        ManagerQueryMsg::ReverseSimulateSwap { ask_token, ask_amount } 
+            => to_binary(&query_reverse_simulate_swap(deps, env, ask_token, ask_amount)?),
+        ManagerQueryMsg::RoleMembers { role, start_after, limit } 
+            => to_binary(&query_role_members(deps, role, start_after, limit)?),
+        ManagerQueryMsg::HasRole { role, address } 
+            => to_binary(&query_has_role(deps, role, address)?),
+        ManagerQueryMsg::StockStatus { id } 
+            => to_binary(&query_stock_status(deps, id)?),
+        ManagerQueryMsg::StockCodeIds { id } 
+            => to_binary(&query_stock_code_ids(deps, id)?),
+        ManagerQueryMsg::MintUsage { id, kind } 
+            => to_binary(&query_mint_usage(deps, env, id, kind)?),
+        ManagerQueryMsg::CallerMinted { id, kind, address } 
+            => to_binary(&query_caller_minted(deps, id, kind, address)?)
+    }
+}
+
+pub fn query_config(deps: Deps, env: Env) -> StdResult<ManagerConfigResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    Ok(ManagerConfigResponse {
+        owner: cfg.owner,
+        stkn_address: cfg.stkn_address.clone(),
+        pusd_address: cfg.pusd_address,
+        staking_address: cfg.staking_address,
+        
+        cw20_code_id: cfg.cw20_code_id,
+        stock_code_id: cfg.stock_code_id,
+        pool_code_id: cfg.pool_code_id,
+
+        staking_code_id: cfg.staking_code_id,
+
+        shorting_code_id: cfg.shorting_code_id,
+        trading_code_id: cfg.trading_code_id,
+        providing_code_id: cfg.providing_code_id,
+
+        price: cfg.price,
+        price_updated_at: cfg.price_updated_at,
+        oracle_address: cfg.oracle_address,
+        max_price_age: cfg.max_price_age,
+        buy_pusd_fee_bps: cfg.buy_pusd_fee_bps,
+        sell_pusd_fee_bps: cfg.sell_pusd_fee_bps,
+        fee_collector: cfg.fee_collector.clone(),
+        accrued_fees: ACCRUED_FEES.load(deps.storage)?,
+        min_stkn_reserve: cfg.min_stkn_reserve,
+        min_collateral_ratio: cfg.min_collateral_ratio,
+        stkn_amount: util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone()).unwrap(),
+        max_stock_id: cfg.max_stock_id,
+        enabled: cfg.enabled,
+        providing_sync_interval: cfg.providing_sync_interval,
+        keeper_reward: cfg.keeper_reward
+    })
+}
+
+pub fn query_stock_by_symbol(deps: Deps, symbol: String) -> StdResult<StockInfo> {
+    let id = STOCK_SYMBOLS.load(deps.storage, &symbol_key(&symbol))?;
+    STOCKS.load(deps.storage, id)
+}
+
+pub fn query_settlement(deps: Deps) -> StdResult<SettlementResponse> {
+    Ok(SettlementResponse {
+        settlement: SETTLEMENT.may_load(deps.storage)?
+    })
+}
+
+pub fn query_solvency(
+    deps: Deps,
+    env: Env,
+    start_after: Option<u32>,
+    limit: Option<u32>
+) -> StdResult<SolvencyResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let pusd_supply = get_pusd_supply(deps, &cfg)?;
+    let stkn_reserve = get_available_stkn(deps, &env, &cfg)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    let collateral_ratio = get_collateral_ratio(deps, &env, &cfg, pusd_supply)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    let stocks = STOCKS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|id| {
+            let id = id?;
+            let pusd_minted = MINT_USAGE
+                .may_load(deps.storage, (id, MintKind::Pusd.as_str()))?
+                .unwrap_or_default();
+            Ok(StockMintedResponse {
+                id,
+                pusd_minted: pusd_minted.total_minted,
+                pusd_outstanding: pusd_minted.outstanding
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(SolvencyResponse {
+        pusd_supply,
+        stkn_reserve,
+        price: cfg.price,
+        reserve_value: stkn_reserve.multiply_ratio(cfg.price, NORMAL_DECIMAL),
+        collateral_ratio,
+        min_collateral_ratio: cfg.min_collateral_ratio,
+        stocks
+    })
+}
+
+pub fn query_stkn_funded(deps: Deps, address: Addr) -> StdResult<Uint128> {
+    Ok(STKN_FUNDERS.may_load(deps.storage, &address)?.unwrap_or_default())
+}
+
+pub fn query_reserve_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>
+) -> StdResult<ReserveHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let records = RESERVE_HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    Ok(ReserveHistoryResponse { records })
+}
+
+pub fn query_price(deps: Deps, env: Env) -> StdResult<PriceResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    get_swap_price(deps, &env, &cfg).map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+pub fn query_simulate_swap(
+    deps: Deps,
+    env: Env,
+    offer_token: Addr,
+    offer_amount: Uint128
+) -> StdResult<SimulateSwapResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let swap = simulate_swap(deps, &env, &cfg, offer_token, offer_amount)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    Ok(SimulateSwapResponse {
+        price: swap.price,
+        offer_amount: swap.offer_amount,
+        return_amount: swap.return_amount,
+        fee_amount: swap.fee_amount
+    })
+}
+
+pub fn query_reverse_simulate_swap(
+    deps: Deps,
+    env: Env,
+    ask_token: Addr,
+    ask_amount: Uint128
+) -> StdResult<SimulateSwapResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let swap = reverse_simulate_swap(deps, &env, &cfg, ask_token, ask_amount)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    Ok(SimulateSwapResponse {
+        price: swap.price,
+        offer_amount: swap.offer_amount,
+        return_amount: swap.return_amount,
+        fee_amount: swap.fee_amount
+    })
+}
+
+pub fn query_bootstrap_status(deps: Deps) -> StdResult<BootstrapStatusResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let bootstrap = BOOTSTRAP.load(deps.storage)?;
+    let stage = bootstrap.stage;
+    Ok(BootstrapStatusResponse {
+        stage,
+        last_error: bootstrap.last_error,
+        pusd_address: if stage == BootstrapStage::Pusd { None } else { Some(cfg.pusd_address) },
+        staking_address: if stage == BootstrapStage::Done { Some(cfg.staking_address) } else { None }
+    })
+}
+
+pub fn query_pending_owner(deps: Deps) -> StdResult<PendingOwnerResponse> {
+    let pending = PENDING_OWNER.may_load(deps.storage)?;
+    Ok(PendingOwnerResponse {
+        pending_owner: pending.clone().map(|p| p.owner),
+        expiry: pending.and_then(|p| p.expiry)
+    })
+}
+
+pub fn query_role_members(
+    deps: Deps,
+    role: Role,
+    start_after: Option<String>,
+    limit: Option<u32>
+) -> StdResult<RoleMembersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into_bytes()));
+
+    let members = ROLES
+        .prefix(role.as_str())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    Ok(RoleMembersResponse { role, members })
+}
+
+pub fn query_has_role(deps: Deps, role: Role, address: Addr) -> StdResult<bool> {
+    Ok(check_role(deps.storage, role, address).is_ok())
+}
+
+pub fn query_stock(deps: Deps, id: u32) -> StdResult<StockInfo> {
+    
+    let stock_info = STOCKS.load(deps.storage, id)?;
+    Ok(stock_info)
+}
+
+pub fn query_stock_status(deps: Deps, id: u32) -> StdResult<StockStatusResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let stock_info = STOCKS.load(deps.storage, id)?;
+    Ok(StockStatusResponse {
+        id,
+        status: stock_info.status,
+        mint_enabled: cfg.enabled && stock_info.status == StockStatus::Active
+    })
+}
+
+pub fn query_stock_code_ids(deps: Deps, id: u32) -> StdResult<StockCodeIdsResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let stock_info = STOCKS.load(deps.storage, id)?;
+
+    let subcontracts = [Subcontract::Stock, Subcontract::Pool, Subcontract::Shorting, Subcontract::Trading, Subcontract::Providing]
+        .iter()
+        .map(|target| {
+            let address = subcontract_address(&stock_info, *target);
+            let contract_info: ContractInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::ContractInfo {
+                contract_addr: address.clone().into(),
+            }))?;
+            Ok(SubcontractCodeInfo {
+                target: *target,
+                address,
+                code_id: contract_info.code_id,
+                latest_code_id: subcontract_code_id(&cfg, *target),
+                admin: contract_info.admin
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(StockCodeIdsResponse { id, subcontracts })
+}
+
+pub fn query_mint_usage(deps: Deps, env: Env, id: u32, kind: MintKind) -> StdResult<MintUsageResponse> {
+    let (limit, usage) = load_mint_usage(deps.storage, &env, id, kind)?;
+    Ok(MintUsageResponse {
+        id,
+        kind,
+        limit,
+        total_minted: usage.total_minted,
+        outstanding: usage.outstanding,
+        window_minted: usage.window.total()?
+    })
+}
+
+pub fn query_caller_minted(deps: Deps, id: u32, kind: MintKind, address: Addr) -> StdResult<Uint128> {
+    Ok(CALLER_MINTED.may_load(deps.storage, (id, kind.as_str(), &address))?.unwrap_or_default())
+}
+
+pub fn query_check_stock_subcontract(deps: Deps, id: u32, address: Addr) -> StdResult<bool> {
+    is_stock_subcontract(deps.storage, id, &address)
+}
+
+pub fn query_subcontract_role(deps: Deps, address: Addr) -> StdResult<SubcontractRoleResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let address_role = ADDRESS_ROLES.may_load(deps.storage, &address)?;
+
+    let is_owner = cfg.owner.as_ref() == Some(&address);
+    let reason = if is_owner {
+        String::from("owner, authorized for every stock")
+    } else {
+        match &address_role {
+            Some(AddressRole { stock_id: None, role }) => format!("{} contract, authorized for every stock", role.as_str()),
+            Some(AddressRole { stock_id: Some(stock_id), role }) => format!("{} contract of stock {}, authorized for this stock only", role.as_str(), stock_id),
+            None => String::from("not registered, not authorized")
+        }
+    };
+
+    Ok(SubcontractRoleResponse {
+        address: address.clone(),
+        is_owner,
+        stock_id: address_role.as_ref().and_then(|address_role| address_role.stock_id),
+        role: address_role.map(|address_role| address_role.role),
+        reason
+    })
+}
+
+pub fn query_corporate_actions(
+    deps: Deps,
+    id: Option<u32>,
+    start_after: Option<u64>,
+    limit: Option<u32>
+) -> StdResult<CorporateActionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let actions = CORPORATE_ACTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, record)) => id.map_or(true, |id| record.action.stock_id() == id),
+            Err(_) => true
+        })
+        .take(limit)
+        .map(|item| item.map(|(_id, record)| record))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(CorporateActionsResponse { actions })
+}
+
+pub fn query_due_for_sync(deps: Deps, env: Env, limit: Option<u32>) -> StdResult<DueForSyncResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let cfg = CONFIG.load(deps.storage)?;
+    let (stocks, _, _) = due_for_sync(deps, &env, &cfg, limit)?;
+    let reward = keeper_reward(deps, &env, &cfg, stocks.len())
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    Ok(DueForSyncResponse { stocks, reward })
+}
+
+pub fn query_multisig(deps: Deps) -> StdResult<MultisigResponse> {
+    let members = MULTISIG_MEMBERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+
+    Ok(MultisigResponse {
+        config: MULTISIG_CONFIG.may_load(deps.storage)?,
+        members
+    })
+}
+
+pub fn query_proposal(deps: Deps, env: Env, id: u64) -> StdResult<Proposal> {
+    let mut proposal = PROPOSALS.load(deps.storage, id)?;
+    proposal.status = proposal.current_status(env.block.time.seconds());
+    Ok(proposal)
+}
+
+pub fn query_list_proposals(
+    deps: Deps,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>
+) -> StdResult<ProposalListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let proposals = PROPOSALS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_id, mut proposal)| {
+            proposal.status = proposal.current_status(env.block.time.seconds());
+            proposal
+        }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ProposalListResponse { proposals })
+}
+
+pub fn query_timelock_delays(deps: Deps) -> StdResult<TimelockDelaysResponse> {
+    let categories = [
+        TimelockCategory::Price,
+        TimelockCategory::Fees,
+        TimelockCategory::CodeIds,
+        TimelockCategory::Listing,
+        TimelockCategory::Treasury,
+        TimelockCategory::Timelock,
+        TimelockCategory::CorporateAction,
+        TimelockCategory::CircuitBreaker,
+        TimelockCategory::Roles,
+    ];
+    let delays = categories
+        .iter()
+        .map(|category| -> StdResult<_> {
+            Ok((*category, TIMELOCK_DELAYS.may_load(deps.storage, category.as_str())?.unwrap_or(0)))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TimelockDelaysResponse { delays })
+}
+
+/// Pending operations by id
+pub fn query_scheduled_operations(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>
+) -> StdResult<ScheduledOperationsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let operations = SCHEDULED_OPERATIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_id, operation)| operation))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ScheduledOperationsResponse { operations })
+}
+
+pub fn query_archived_stocks(
+    deps: Deps,
+    start_after: Option<u32>,
+    limit: Option<u32>
+) -> StdResult<ArchivedStockListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let list = ARCHIVED_STOCKS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_id, archived)| archived))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ArchivedStockListResponse { list })
+}
+
+pub fn query_list_stocks(
+    deps: Deps,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+    status: Option<StockStatus>,
+    symbol_prefix: Option<String>
+) -> StdResult<StockListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    // Symbols match case-insensitively, like the symbol index
+    let symbol_prefix = symbol_prefix.map(|prefix| symbol_key(&prefix));
+
+    // Filters read at most MAX_SCAN stocks per call, the caller continues from next_start_after
+    let mut list = vec![];
+    let mut scanned = 0usize;
+    let mut last_scanned = None;
+    for item in STOCKS.range(deps.storage, start, None, Order::Ascending).take(MAX_SCAN) {
+        let (id, stock_info) = item?;
+        scanned += 1;
+        last_scanned = Some(id);
+        if status.map_or(true, |status| stock_info.status == status)
+            && symbol_prefix.as_ref().map_or(true, |prefix| symbol_key(&stock_info.symbol).starts_with(prefix.as_str()))
+        {
+            list.push(stock_info);
+            if list.len() >= limit {
+                break;
+            }
+        }
+    }
+
+    let next_start_after = if list.len() >= limit || scanned >= MAX_SCAN { last_scanned } else { None };
+    Ok(StockListResponse { list, next_start_after })
+}
+
+pub fn query_stock_count(deps: Deps) -> StdResult<StockCountResponse> {
+    Ok(StockCountResponse {
+        count: STOCK_COUNT.load(deps.storage)?
+    })
+}
+
+fn map_stock(
+    item: StdResult<(u32, StockInfo)>,
+) -> StdResult<StockInfo> {
+    item.map(|(_id, stock_info)| {
+        stock_info
+    })
+}
+
+
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version = get_contract_version(deps.storage)?;
+    if version.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: version.contract,
+        });
+    }
+    let original_version =
+        ensure_from_older_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    if original_version < "1.1.0".parse::<semver::Version>().unwrap() {
+        migration::migrate_from_v1_0(deps.branch(), &env)?;
+    }
+    if let Some(pusd_url) = msg.pusd_url {
+        BOOTSTRAP.update(deps.storage, |mut bootstrap| -> StdResult<_> {
+            bootstrap.pusd.url = pusd_url;
+            Ok(bootstrap)
+        })?;
+    }
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", original_version.to_string())
+        .add_attribute("to_version", CONTRACT_VERSION)
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{ContractResult, OwnedDeps, QuerierResult, SubMsgResponse, SubMsgResult, SystemError, SystemResult};
+    use cw20::{BalanceResponse, EmbeddedLogo};
+
+    use super::*;
+    use crate::state::{PusdConfig, StakingConfig};
+
+    type MockDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+    const OWNER: &str = "owner";
+    const STKN_BALANCE: u128 = 1_000_000_000;
+    const PUSD_SUPPLY: u128 = 500_000_000;
+    const STOCK_SUPPLY: u128 = 1_000_000_000;
+    const STOCK_PRICE: u128 = 10_000_000;
+
+    /// The manager answers its own config query with `config`, "pusd", "stkn" and the stock tokens
+    /// answer cw20 queries, and anything else is a stock token asked for its config
+    fn mock_querier(config: Option<ManagerConfigResponse>, stock_price: Uint128) -> impl Fn(&WasmQuery) -> QuerierResult {
+        move |query| match query {
+            WasmQuery::Smart { contract_addr, msg } => {
+                // only the trading contracts of "busy" stocks have open positions
+                if let Ok(SubcontractQueryMsg::OpenPositions {}) = from_binary(msg) {
+                    let count = if contract_addr.starts_with("busy") && contract_addr.ends_with("_trading") { 2 } else { 0 };
+                    return SystemResult::Ok(ContractResult::Ok(to_binary(&OpenPositionsResponse { count }).unwrap()));
+                }
+                let response = match (contract_addr.as_str(), from_binary::<Cw20QueryMsg>(msg)) {
+                    (MOCK_CONTRACT_ADDR, _) => to_binary(config.as_ref().expect("manager config")),
+                    (_, Ok(Cw20QueryMsg::Balance { .. })) => to_binary(&BalanceResponse {
+                        balance: Uint128::new(STKN_BALANCE)
+                    }),
+                    (token, Ok(Cw20QueryMsg::Minter {})) => to_binary(&Some(MinterResponse {
+                        minter: if token.starts_with("foreign") { "someone" } else { MOCK_CONTRACT_ADDR }.to_string(),
+                        cap: None
+                    })),
+                    (token, Ok(Cw20QueryMsg::TokenInfo {})) => to_binary(&TokenInfoResponse {
+                        name: token.to_uppercase(),
+                        symbol: token.to_uppercase(),
+                        decimals: if token.ends_with("18") { 18 } else { 6 },
+                        total_supply: Uint128::new(if token == "pusd" { PUSD_SUPPLY } else { STOCK_SUPPLY })
+                    }),
+                    // "legacy" tokens were listed with the owner as marketing address
+                    (token, Ok(Cw20QueryMsg::MarketingInfo {})) => to_binary(&MarketingInfoResponse {
+                        project: None,
+                        description: None,
+                        logo: None,
+                        marketing: Some(Addr::unchecked(if token.starts_with("legacy") { OWNER } else { MOCK_CONTRACT_ADDR }))
+                    }),
+                    (token, _) => to_binary(&StockConfigResponse {
+                        id: 1,
+                        manager_address: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                        pool_address: Addr::unchecked(format!("{}_pool", token)),
+                        shorting_address: Addr::unchecked(format!("{}_shorting", token)),
+                        trading_address: Addr::unchecked(format!("{}_trading", token)),
+                        providing_address: Addr::unchecked(format!("{}_providing", token)),
+                        price: stock_price
+                    }),
+                };
+                SystemResult::Ok(ContractResult::Ok(response.unwrap()))
+            }
+            // the manager administers and mints everything except the "foreign" contracts
+            WasmQuery::ContractInfo { contract_addr } => {
+                let admin = if contract_addr.starts_with("foreign") { "someone" } else { MOCK_CONTRACT_ADDR };
+                let response = format!(r#"{{"code_id":1,"creator":"{}","admin":"{}","pinned":false}}"#, OWNER, admin);
+                SystemResult::Ok(ContractResult::Ok(Binary::from(response.into_bytes())))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest { kind: "wasm".to_string() }),
+        }
+    }
+
+    /// Points the manager self-query at the stored config, so owner and enabled checks follow the state
+    fn refresh_querier(deps: &mut MockDeps, stock_price: Uint128) {
+        let config = query_config(deps.as_ref(), mock_env()).unwrap();
+        deps.querier.update_wasm(mock_querier(Some(config), stock_price));
+    }
+
+    fn exec_at(deps: &mut MockDeps, env: Env, sender: &str, msg: ExecuteMsg) -> Result<Response, ContractError> {
+        refresh_querier(deps, Uint128::new(STOCK_PRICE));
+        execute(deps.as_mut(), env, mock_info(sender, &[]), msg)
+    }
+
+    fn exec(deps: &mut MockDeps, sender: &str, msg: ExecuteMsg) -> Result<Response, ContractError> {
+        exec_at(deps, mock_env(), sender, msg)
+    }
+
+    fn later(seconds: u64) -> Env {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        env
+    }
+
+    fn receive(sender: &str, amount: u128, msg: &ReceiveMsg) -> ExecuteMsg {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(msg).unwrap()
+        })
+    }
+
+    fn add_stock(deps: &mut MockDeps, id: u32, stock: &str) {
+        let stock_info = StockInfo {
+            id,
+            symbol: stock.to_uppercase(),
+            stock_address: Addr::unchecked(stock),
+            pool_address: Addr::unchecked(format!("{}_pool", stock)),
+            shorting_address: Addr::unchecked(format!("{}_shorting", stock)),
+            trading_address: Addr::unchecked(format!("{}_trading", stock)),
+            providing_address: Addr::unchecked(format!("{}_providing", stock)),
+            status: StockStatus::Active,
+            metadata: StockMetadata::default()
+        };
+        STOCKS.save(&mut deps.storage, id, &stock_info).unwrap();
+        STOCK_SYMBOLS.save(&mut deps.storage, &symbol_key(stock), &id).unwrap();
+        register_stock_addresses(&mut deps.storage, &stock_info).unwrap();
+        STOCK_COUNT.save(&mut deps.storage, &id).unwrap();
+        CONFIG.update(&mut deps.storage, |mut cfg| -> StdResult<_> {
+            cfg.max_stock_id = id;
+            Ok(cfg)
+        }).unwrap();
+    }
+
+    /// Manual price of 2 PUSD, 1% swap fees, adopting "pusd" and "staking"
+    fn instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            stkn_address: Addr::unchecked("stkn"),
+            cw20_code_id: 1,
+            stock_code_id: 2,
+            pool_code_id: 3,
+            staking_code_id: 4,
+            shorting_code_id: 5,
+            trading_code_id: 6,
+            providing_code_id: 7,
+            price: Uint128::new(2_000_000),
+            oracle_address: None,
+            max_price_age: None,
+            buy_pusd_fee_bps: 100,
+            sell_pusd_fee_bps: 100,
+            fee_collector: None,
+            pusd: PusdConfig {
+                name: String::from("PUSD"),
+                symbol: String::from("PUSD"),
+                cap: None,
+                url: String::from("https://stocken.io/pusd.png"),
+                label: String::from("PUSD")
+            },
+            staking: StakingConfig {
+                lock_days: vec![30],
+                ratios: vec![20000],
+                label: String::from("STKN Staking")
+            },
+            pusd_address: Some(Addr::unchecked("pusd")),
+            staking_address: Some(Addr::unchecked("staking")),
+            providing_sync_interval: 600
+        }
+    }
+
+    /// Manager from instantiate_msg with the stock "aapl" as id 1
+    fn setup() -> MockDeps {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(mock_querier(None, Uint128::new(STOCK_PRICE)));
+        instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), instantiate_msg()).unwrap();
+        add_stock(&mut deps, 1, "aapl");
+        refresh_querier(&mut deps, Uint128::new(STOCK_PRICE));
+        deps
+    }
+
+    #[test]
+    fn roles_are_granted_and_revoked_by_the_owner() {
+        let mut deps = setup();
+        let pause = ExecuteMsg::UpdateEnabled { enabled: false };
+        let grant = ExecuteMsg::GrantRole { role: Role::Pauser, address: Addr::unchecked("pauser") };
+
+        let err = exec(&mut deps, "pauser", pause.clone()).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Pauser.as_str().to_string() });
+        let err = exec(&mut deps, "pauser", grant.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        exec(&mut deps, OWNER, grant).unwrap();
+        assert!(query_has_role(deps.as_ref(), Role::Pauser, Addr::unchecked("pauser")).unwrap());
+        assert_eq!(query_role_members(deps.as_ref(), Role::Pauser, None, None).unwrap().members, vec![Addr::unchecked("pauser")]);
+        exec(&mut deps, "pauser", pause.clone()).unwrap();
+        assert!(!CONFIG.load(&deps.storage).unwrap().enabled);
+
+        // a role only covers its own actions
+        let err = exec(&mut deps, "pauser", ExecuteMsg::UpdatePrice { price: Uint128::new(1) }).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::PriceSetter.as_str().to_string() });
+
+        exec(&mut deps, OWNER, ExecuteMsg::RevokeRole { role: Role::Pauser, address: Addr::unchecked("pauser") }).unwrap();
+        let err = exec(&mut deps, "pauser", pause).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Pauser.as_str().to_string() });
+        assert!(query_role_members(deps.as_ref(), Role::Pauser, None, None).unwrap().members.is_empty());
+    }
+
+    #[test]
+    fn stock_status_limits_mints_and_transfers() {
+        let mut deps = setup();
+        let status = |status: StockStatus| ExecuteMsg::UpdateStockStatus { id: 1, status };
+        let mint_pusd = || ExecuteMsg::MintPusd { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(100) };
+        let mint_stock = || ExecuteMsg::MintStock { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(100) };
+        let transfer = || ExecuteMsg::TransferStkn { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(100) };
+        let not_active = |status: StockStatus| ContractError::StockNotActive { id: 1, status: status.as_str().to_string() };
+
+        let err = exec(&mut deps, "user", status(StockStatus::Paused)).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Pauser.as_str().to_string() });
+
+        // reduce only still pays out but mints no new stock tokens
+        exec(&mut deps, OWNER, status(StockStatus::ReduceOnly)).unwrap();
+        assert_eq!(exec(&mut deps, "aapl_pool", mint_stock()).unwrap_err(), not_active(StockStatus::ReduceOnly));
+        exec(&mut deps, "aapl_pool", mint_pusd()).unwrap();
+        exec(&mut deps, "aapl_pool", transfer()).unwrap();
+        assert!(!query_stock_status(deps.as_ref(), 1).unwrap().mint_enabled);
+
+        exec(&mut deps, OWNER, status(StockStatus::Paused)).unwrap();
+        assert_eq!(exec(&mut deps, "aapl_pool", mint_pusd()).unwrap_err(), not_active(StockStatus::Paused));
+        assert_eq!(exec(&mut deps, "aapl_pool", transfer()).unwrap_err(), not_active(StockStatus::Paused));
+
+        exec(&mut deps, OWNER, status(StockStatus::Active)).unwrap();
+        exec(&mut deps, "aapl_pool", mint_stock()).unwrap();
+        assert!(query_stock_status(deps.as_ref(), 1).unwrap().mint_enabled);
+
+        // delisting only goes through DelistStock
+        let err = exec(&mut deps, OWNER, status(StockStatus::Delisted)).unwrap_err();
+        assert_eq!(err, ContractError::StockDelisted { id: 1 });
+    }
+
+    #[test]
+    fn list_stocks_pages_and_filters() {
+        let mut deps = setup();
+        for id in 2..=40 {
+            add_stock(&mut deps, id, &format!("stock{}", id));
+        }
+        add_stock(&mut deps, 41, "msft");
+
+        let page = query_list_stocks(deps.as_ref(), None, None, None, None).unwrap();
+        assert_eq!(page.list.len(), DEFAULT_LIMIT as usize);
+        assert_eq!(page.next_start_after, Some(DEFAULT_LIMIT));
+        let page = query_list_stocks(deps.as_ref(), Some(30), Some(100), None, None).unwrap();
+        assert_eq!(page.list.iter().map(|stock| stock.id).collect::<Vec<_>>(), (31..=41).collect::<Vec<_>>());
+        assert_eq!(page.next_start_after, None);
+        let page = query_list_stocks(deps.as_ref(), None, Some(100), None, None).unwrap();
+        assert_eq!(page.list.len(), MAX_LIMIT as usize);
+
+        // the prefix is matched regardless of case
+        for prefix in ["ms", "MS"] {
+            let page = query_list_stocks(deps.as_ref(), None, None, None, Some(prefix.to_string())).unwrap();
+            assert_eq!(page.list.iter().map(|stock| stock.id).collect::<Vec<_>>(), vec![41]);
+            assert_eq!(page.next_start_after, None);
+        }
+
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateStockStatus { id: 41, status: StockStatus::Paused }).unwrap();
+        let page = query_list_stocks(deps.as_ref(), None, None, Some(StockStatus::Paused), None).unwrap();
+        assert_eq!(page.list.iter().map(|stock| stock.id).collect::<Vec<_>>(), vec![41]);
+        assert_eq!(query_stock_count(deps.as_ref()).unwrap().count, 41);
+    }
+
+    #[test]
+    fn symbols_are_unique_and_follow_renames() {
+        let mut deps = setup();
+        add_stock(&mut deps, 2, "msft");
+        let add = |symbol: &str| ExecuteMsg::AddStock {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            url: String::from("https://stocken.io/stock.png"),
+            metadata: None
+        };
+
+        let err = exec(&mut deps, OWNER, add("Aapl")).unwrap_err();
+        assert_eq!(err, ContractError::DuplicateSymbol { symbol: "Aapl".to_string() });
+        let err = exec(&mut deps, OWNER, add("A1")).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSymbol { symbol: "A1".to_string() });
+
+        // the symbol is reserved before the token is instantiated
+        let res = exec(&mut deps, OWNER, add("TSLA")).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(STOCK_SYMBOLS.load(&deps.storage, "TSLA").unwrap(), 3);
+
+        let rename = |id: u32, symbol: &str| ExecuteMsg::UpdateStockSymbol { id, symbol: symbol.to_string() };
+        let err = exec(&mut deps, OWNER, rename(2, "aapl")).unwrap_err();
+        assert_eq!(err, ContractError::DuplicateSymbol { symbol: "aapl".to_string() });
+        exec(&mut deps, OWNER, rename(1, "apple")).unwrap();
+        assert_eq!(query_stock_by_symbol(deps.as_ref(), "APPLE".to_string()).unwrap().id, 1);
+        assert!(query_stock_by_symbol(deps.as_ref(), "aapl".to_string()).is_err());
+        exec(&mut deps, OWNER, rename(2, "aapl")).unwrap();
+        assert_eq!(query_stock_by_symbol(deps.as_ref(), "AAPL".to_string()).unwrap().id, 2);
+    }
+
+    fn swap_msg(min_output: Option<u128>, max_price: Option<u128>, deadline: Option<Expiration>) -> ReceiveMsg {
+        ReceiveMsg::Swap {
+            min_output: min_output.map(Uint128::new),
+            max_price: max_price.map(Uint128::new),
+            deadline,
+            recipient: None
+        }
+    }
+
+    #[test]
+    fn swap_stkn_for_pusd_checks_min_output_and_deadline() {
+        let mut deps = setup();
+
+        // 100 STKN less 1% fee at 2 PUSD
+        let err = exec(&mut deps, "stkn", receive("user", 100_000_000, &swap_msg(Some(198_000_001), None, None))).unwrap_err();
+        assert_eq!(err, ContractError::SlippageExceeded {
+            output: Uint128::new(198_000_000),
+            min_output: Uint128::new(198_000_001)
+        });
+        let res = exec(&mut deps, "stkn", receive("user", 100_000_000, &swap_msg(Some(198_000_000), None, None))).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.attributes[0], attr("action", "buy_pusd"));
+
+        let expired = Some(Expiration::AtHeight(mock_env().block.height));
+        let err = exec(&mut deps, "stkn", receive("user", 100_000_000, &swap_msg(None, None, expired))).unwrap_err();
+        assert_eq!(err, ContractError::SwapExpired {});
+    }
+
+    #[test]
+    fn swap_pusd_for_stkn_checks_max_price() {
+        let mut deps = setup();
+
+        let err = exec(&mut deps, "pusd", receive("user", 200_000_000, &swap_msg(None, Some(1_900_000), None))).unwrap_err();
+        assert_eq!(err, ContractError::PriceTooHigh { price: Uint128::new(2_000_000), max_price: Uint128::new(1_900_000) });
+
+        // 200 PUSD buy 100 STKN, 1 STKN of it is the fee
+        let res = exec(&mut deps, "pusd", receive("user", 200_000_000, &swap_msg(Some(99_000_000), Some(2_000_000), None))).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.attributes[0], attr("action", "buy_stkn"));
+        assert_eq!(res.attributes[4], attr("amount", "99000000"));
+
+        let err = exec(&mut deps, "aapl", receive("user", 200_000_000, &swap_msg(None, None, None))).unwrap_err();
+        assert_eq!(err, ContractError::UnacceptableToken {});
+    }
+
+    #[test]
+    fn reverse_simulation_covers_the_fee() {
+        let deps = setup();
+        let cfg = CONFIG.load(&deps.storage).unwrap();
+
+        for (ask_token, ask_amount) in [("pusd", 198_000_000u128), ("stkn", 99_000_000), ("pusd", 1_234_567)] {
+            let reverse = reverse_simulate_swap(deps.as_ref(), &mock_env(), &cfg, Addr::unchecked(ask_token), Uint128::new(ask_amount)).unwrap();
+            let offer_token = if ask_token == "pusd" { "stkn" } else { "pusd" };
+            let swap = simulate_swap(deps.as_ref(), &mock_env(), &cfg, Addr::unchecked(offer_token), reverse.offer_amount).unwrap();
+            assert!(swap.return_amount >= Uint128::new(ask_amount));
+        }
+    }
+
+    #[test]
+    fn fees_are_capped_and_withdrawn_to_the_collector() {
+        let mut deps = setup();
+        let update_fees = |bps: u64| ExecuteMsg::UpdateFees {
+            buy_pusd_fee_bps: bps,
+            sell_pusd_fee_bps: bps,
+            fee_collector: Addr::unchecked("collector")
+        };
+
+        let err = exec(&mut deps, "user", update_fees(50)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = exec(&mut deps, OWNER, update_fees(MAX_FEE_BPS + 1)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidFee { max_bps: MAX_FEE_BPS });
+        exec(&mut deps, OWNER, update_fees(50)).unwrap();
+        let cfg = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!((cfg.buy_pusd_fee_bps, cfg.sell_pusd_fee_bps), (50, 50));
+
+        // 0.5% of 100 STKN stays with the manager
+        exec(&mut deps, "stkn", receive("user", 100_000_000, &swap_msg(None, None, None))).unwrap();
+        assert_eq!(ACCRUED_FEES.load(&deps.storage).unwrap(), Uint128::new(500_000));
+
+        let withdraw = |amount: Option<u128>| ExecuteMsg::WithdrawFees { amount: amount.map(Uint128::new) };
+        let err = exec(&mut deps, "user", withdraw(None)).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Treasurer.as_str().to_string() });
+        let err = exec(&mut deps, OWNER, withdraw(Some(500_001))).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientFees {});
+        let res = exec(&mut deps, OWNER, withdraw(None)).unwrap();
+        assert_eq!(res.attributes[1], attr("recipient", "collector"));
+        assert_eq!(res.attributes[2], attr("amount", "500000"));
+        assert!(ACCRUED_FEES.load(&deps.storage).unwrap().is_zero());
+    }
+
+    #[test]
+    fn mint_ceiling_counts_outstanding_and_window_rolls() {
+        let mut deps = setup();
+        let caller = Addr::unchecked("aapl_pool");
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateMintLimit {
+            id: 1,
+            kind: MintKind::Pusd,
+            limit: MintLimit {
+                ceiling: Some(Uint128::new(100)),
+                window: 1000,
+                window_limit: Some(Uint128::new(150))
+            }
+        }).unwrap();
+        let env = mock_env();
+
+        record_mint(&mut deps.storage, &env, 1, MintKind::Pusd, &caller, Uint128::new(80)).unwrap();
+        let err = record_mint(&mut deps.storage, &env, 1, MintKind::Pusd, &caller, Uint128::new(30)).unwrap_err();
+        assert_eq!(err, ContractError::MintCeilingExceeded { id: 1 });
+
+        // burns free the ceiling but not the window
+        record_burn(&mut deps.storage, 1, MintKind::Pusd, Uint128::new(50)).unwrap();
+        record_mint(&mut deps.storage, &env, 1, MintKind::Pusd, &caller, Uint128::new(60)).unwrap();
+        record_burn(&mut deps.storage, 1, MintKind::Pusd, Uint128::new(50)).unwrap();
+        let err = record_mint(&mut deps.storage, &env, 1, MintKind::Pusd, &caller, Uint128::new(20)).unwrap_err();
+        assert_eq!(err, ContractError::MintRateLimitExceeded { id: 1 });
+
+        let env = later(1100);
+        record_mint(&mut deps.storage, &env, 1, MintKind::Pusd, &caller, Uint128::new(20)).unwrap();
+        let usage = query_mint_usage(deps.as_ref(), env, 1, MintKind::Pusd).unwrap();
+        assert_eq!(usage.total_minted, Uint128::new(160));
+        assert_eq!(usage.outstanding, Uint128::new(60));
+        assert_eq!(usage.window_minted, Uint128::new(20));
+        assert_eq!(query_caller_minted(deps.as_ref(), 1, MintKind::Pusd, caller).unwrap(), Uint128::new(160));
+    }
+
+    #[test]
+    fn burn_is_reported_by_subcontracts_only() {
+        let mut deps = setup();
+        exec(&mut deps, "aapl_pool", ExecuteMsg::MintPusd { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(100) }).unwrap();
+
+        let burn = ReceiveMsg::Burn { id: 1 };
+        let err = exec(&mut deps, "pusd", receive("user", 40, &burn)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = exec(&mut deps, "stkn", receive("aapl_pool", 40, &burn)).unwrap_err();
+        assert_eq!(err, ContractError::UnacceptableToken {});
+
+        let res = exec(&mut deps, "pusd", receive("aapl_pool", 40, &burn)).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(MINT_USAGE.load(&deps.storage, (1, MintKind::Pusd.as_str())).unwrap().outstanding, Uint128::new(60));
+    }
+
+    #[test]
+    fn withdraw_stkn_keeps_the_min_reserve_and_records_history() {
+        let mut deps = setup();
+        let withdraw = |amount: u128| ExecuteMsg::WithdrawStkn { amount: Uint128::new(amount), recipient: Addr::unchecked("treasury") };
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateMinStknReserve { amount: Uint128::new(400_000_000) }).unwrap();
+
+        let err = exec(&mut deps, "user", withdraw(1)).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Treasurer.as_str().to_string() });
+        let err = exec(&mut deps, OWNER, withdraw(STKN_BALANCE - 400_000_000 + 1)).unwrap_err();
+        assert_eq!(err, ContractError::BelowMinReserve {});
+        let err = exec(&mut deps, OWNER, withdraw(u128::MAX)).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+
+        exec_at(&mut deps, later(10), OWNER, withdraw(STKN_BALANCE - 400_000_000)).unwrap();
+        let history = query_reserve_history(deps.as_ref(), None, None).unwrap();
+        assert_eq!(history.records, vec![(1, ReserveRecord {
+            action: String::from("withdraw"),
+            address: Addr::unchecked("treasury"),
+            amount: Uint128::new(600_000_000),
+            balance: Uint128::new(400_000_000),
+            time: later(10).block.time.seconds()
+        })]);
+        assert!(query_reserve_history(deps.as_ref(), Some(1), None).unwrap().records.is_empty());
+    }
+
+    #[test]
+    fn solvency_reports_the_collateral_ratio_and_guards_mints() {
+        let mut deps = setup();
+        let mint = || ExecuteMsg::MintPusd { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(100_000_000) };
+        exec(&mut deps, "aapl_pool", mint()).unwrap();
+
+        // 1000 STKN at 2 PUSD back 500 PUSD
+        let solvency = query_solvency(deps.as_ref(), mock_env(), None, None).unwrap();
+        assert_eq!(solvency.pusd_supply, Uint128::new(PUSD_SUPPLY));
+        assert_eq!(solvency.stkn_reserve, Uint128::new(STKN_BALANCE));
+        assert_eq!(solvency.reserve_value, Uint128::new(2 * STKN_BALANCE));
+        assert_eq!(solvency.collateral_ratio, Some(Decimal::from_ratio(4u128, 1u128)));
+        assert_eq!(solvency.stocks, vec![StockMintedResponse {
+            id: 1,
+            pusd_minted: Uint128::new(100_000_000),
+            pusd_outstanding: Uint128::new(100_000_000)
+        }]);
+
+        // the ratio after the mint counts
+        let min_ratio = |ratio: u64| ExecuteMsg::UpdateMinCollateralRatio { ratio: Some(Decimal::percent(ratio)) };
+        exec(&mut deps, OWNER, min_ratio(350)).unwrap();
+        let err = exec(&mut deps, "aapl_pool", mint()).unwrap_err();
+        assert_eq!(err, ContractError::CollateralRatioTooLow {});
+        exec(&mut deps, OWNER, min_ratio(300)).unwrap();
+        exec(&mut deps, "aapl_pool", mint()).unwrap();
+    }
+
+    #[test]
+    fn settlement_pays_stock_holders_up_to_the_snapshot() {
+        let mut deps = setup();
+
+        let err = exec(&mut deps, "user", ExecuteMsg::GlobalSettlement {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        exec(&mut deps, OWNER, ExecuteMsg::GlobalSettlement {}).unwrap();
+
+        let settlement = SETTLEMENT.load(&deps.storage).unwrap();
+        let total_claims = PUSD_SUPPLY + STOCK_SUPPLY * STOCK_PRICE / NORMAL_DECIMAL;
+        assert!(settlement.complete);
+        assert_eq!(settlement.total_claims, Uint128::new(total_claims));
+        assert_eq!(settlement.redemption_rate, Decimal::from_ratio(STKN_BALANCE, total_claims));
+
+        let settle = ReceiveMsg::SettleStock { recipient: None };
+        let res = exec(&mut deps, "aapl", receive("holder", 100_000_000, &settle)).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.attributes[5], attr("amount", "1000000000"));
+
+        let err = exec(&mut deps, "aapl", receive("holder", STOCK_SUPPLY, &settle)).unwrap_err();
+        assert_eq!(err, ContractError::RedemptionCapExceeded { id: 1 });
+        let err = exec(&mut deps, "stkn", receive("holder", 100, &settle)).unwrap_err();
+        assert_eq!(err, ContractError::UnacceptableToken {});
+
+        let mint = ExecuteMsg::MintPusd { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(100) };
+        assert_eq!(exec(&mut deps, "aapl_pool", mint).unwrap_err(), ContractError::Settled {});
+    }
+
+    #[test]
+    fn settlement_snapshots_stocks_in_batches() {
+        let mut deps = setup();
+        for id in 2..=(DEFAULT_LIMIT + 2) {
+            add_stock(&mut deps, id, &format!("stock{}", id));
+        }
+        exec(&mut deps, OWNER, ExecuteMsg::GlobalSettlement {}).unwrap();
+        assert!(!SETTLEMENT.load(&deps.storage).unwrap().complete);
+
+        let redeem = ReceiveMsg::RedeemPusd { recipient: None };
+        let err = exec(&mut deps, "pusd", receive("holder", 100, &redeem)).unwrap_err();
+        assert_eq!(err, ContractError::SettlementIncomplete {});
+        let settle = ReceiveMsg::SettleStock { recipient: None };
+        let err = exec(&mut deps, "aapl", receive("holder", 100, &settle)).unwrap_err();
+        assert_eq!(err, ContractError::SettlementIncomplete {});
+
+        exec(&mut deps, "keeper", ExecuteMsg::SettleStocks { limit: None }).unwrap();
+        let settlement = SETTLEMENT.load(&deps.storage).unwrap();
+        assert!(settlement.complete);
+        let stock_claims = STOCK_SUPPLY * STOCK_PRICE / NORMAL_DECIMAL * (DEFAULT_LIMIT as u128 + 2);
+        assert_eq!(settlement.total_claims, Uint128::new(PUSD_SUPPLY + stock_claims));
+
+        let err = exec(&mut deps, "keeper", ExecuteMsg::SettleStocks { limit: None }).unwrap_err();
+        assert_eq!(err, ContractError::SettlementComplete {});
+        let res = exec(&mut deps, "pusd", receive("holder", 100_000_000, &redeem)).unwrap();
+        assert_eq!(res.messages.len(), 2);
+    }
+
+    #[test]
+    fn migrate_stocks_needs_the_owner_and_the_admin_role() {
+        let mut deps = setup();
+        add_stock(&mut deps, 2, "foreign");
+        let migrate = |ids: Vec<u32>| ExecuteMsg::MigrateStocks {
+            ids,
+            target: Subcontract::Pool,
+            migrate_msg: Binary::from(b"{}".to_vec())
+        };
+
+        let err = exec(&mut deps, "user", migrate(vec![1])).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = exec(&mut deps, OWNER, migrate(vec![])).unwrap_err();
+        assert_eq!(err, ContractError::InvalidInput {});
+        let err = exec(&mut deps, OWNER, migrate(vec![1, 2])).unwrap_err();
+        assert_eq!(err, ContractError::NotAdmin { address: "foreign_pool".to_string() });
+
+        let res = exec(&mut deps, OWNER, migrate(vec![1])).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Migrate {
+            contract_addr: String::from("aapl_pool"),
+            new_code_id: 3,
+            msg: Binary::from(b"{}".to_vec())
+        }));
+    }
+
+    /// Reply of an instantiation, None when it failed
+    fn instantiate_reply(id: u64, contract_address: Option<&str>) -> Reply {
+        let result = match contract_address {
+            // MsgInstantiateContractResponse with only its contract_address field
+            Some(contract_address) => {
+                let mut data = vec![0x0a, contract_address.len() as u8];
+                data.extend_from_slice(contract_address.as_bytes());
+                SubMsgResult::Ok(SubMsgResponse { events: vec![], data: Some(Binary::from(data)) })
+            },
+            None => SubMsgResult::Err(String::from("out of gas"))
+        };
+        Reply { id, result }
+    }
+
+    #[test]
+    fn failed_bootstrap_stage_is_recorded_and_resumed() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(mock_querier(None, Uint128::new(STOCK_PRICE)));
+        let res = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), InstantiateMsg {
+            pusd_address: None,
+            staking_address: None,
+            ..instantiate_msg()
+        }).unwrap();
+        assert_eq!(res.messages[0].id, INSTANTIATE_PUSD_ID);
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Always);
+
+        let res = reply(deps.as_mut(), mock_env(), instantiate_reply(INSTANTIATE_PUSD_ID, None)).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "bootstrap_failed"));
+        let status = query_bootstrap_status(deps.as_ref()).unwrap();
+        assert_eq!(status.stage, BootstrapStage::Pusd);
+        assert!(status.last_error.is_some());
+        let err = reply(deps.as_mut(), mock_env(), instantiate_reply(INSTANTIATE_STAKING_ID, Some("staking"))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidTokenReplyId {});
 
-    match msg {
-        ReceiveMsg::FundStkn {} => {
-            // Update Amount
-            if info.sender != cfg.stkn_address {
-                return Err(ContractError::UnacceptableToken {});
-            }
-            
-            return Ok(Response::new()
-                .add_attributes(vec![
-                    attr("action", "fund_stkn"),
-                    attr("address", user_addr),
-                    attr("amount", wrapper.amount)
-                ])); 
-            
-        },
-        ReceiveMsg::Swap {expected_amount} => {
-            if info.sender != cfg.stkn_address.clone() && info.sender != cfg.pusd_address.clone() {
-                return Err(ContractError::UnacceptableToken {});
-            }
+        let add_stock = ExecuteMsg::AddStock {
+            name: String::from("Apple"),
+            symbol: String::from("AAPL"),
+            url: String::from("https://stocken.io/aapl.png"),
+            metadata: None
+        };
+        assert_eq!(exec(&mut deps, OWNER, add_stock.clone()).unwrap_err(), ContractError::BootstrapNotDone {});
+        assert_eq!(exec(&mut deps, "user", ExecuteMsg::ResumeBootstrap {}).unwrap_err(), ContractError::Unauthorized {});
+        let res = exec(&mut deps, OWNER, ExecuteMsg::ResumeBootstrap {}).unwrap();
+        assert_eq!(res.messages[0].id, INSTANTIATE_PUSD_ID);
 
-            let mut messages:Vec<CosmosMsg> = vec![];
-            if info.sender == cfg.stkn_address.clone() {
-                // Store stkn and mint according pusd to user_addr
-                
-                let mut pusd_amount = cfg.price * wrapper.amount / Uint128::from(NORMAL_DECIMAL);
+        // each successful stage starts the next one
+        let res = reply(deps.as_mut(), mock_env(), instantiate_reply(INSTANTIATE_PUSD_ID, Some("pusd"))).unwrap();
+        assert_eq!(res.messages[0].id, INSTANTIATE_STAKING_ID);
+        reply(deps.as_mut(), mock_env(), instantiate_reply(INSTANTIATE_STAKING_ID, Some("staking"))).unwrap();
+        let status = query_bootstrap_status(deps.as_ref()).unwrap();
+        assert_eq!(status.stage, BootstrapStage::Done);
+        assert_eq!(status.last_error, None);
+        assert_eq!(status.pusd_address, Some(Addr::unchecked("pusd")));
+        assert_eq!(exec(&mut deps, OWNER, ExecuteMsg::ResumeBootstrap {}).unwrap_err(), ContractError::BootstrapDone {});
 
-                if expected_amount * Uint128::from(NORMAL_DECIMAL) / cfg.price == wrapper.amount {
-                    pusd_amount = expected_amount;
-                }
+        let res = exec(&mut deps, OWNER, add_stock).unwrap();
+        reply(deps.as_mut(), mock_env(), instantiate_reply(res.messages[0].id, Some("aapl"))).unwrap();
+        assert_eq!(query_stock_by_symbol(deps.as_ref(), "aapl".to_string()).unwrap().stock_address, Addr::unchecked("aapl"));
+        assert_eq!(query_stock_count(deps.as_ref()).unwrap().count, 1);
 
-                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: cfg.pusd_address.clone().into(),
-                    funds: vec![],
-                    msg: to_binary(&Cw20ExecuteMsg::Mint {
-                        recipient: user_addr.clone().into(),
-                        amount: pusd_amount
-                    })?,
-                }));
-                return Ok(Response::new()
-                    .add_messages(messages)
-                    .add_attributes(vec![
-                        attr("action", "buy_pusd"),
-                        attr("address", user_addr),
-                        attr("amount", pusd_amount)
-                    ]));
+        // a failed stock instantiation fails the reply, which reverts AddStock as a whole
+        let res = exec(&mut deps, OWNER, ExecuteMsg::AddStock {
+            name: String::from("Microsoft"),
+            symbol: String::from("MSFT"),
+            url: String::from("https://stocken.io/msft.png"),
+            metadata: None
+        }).unwrap();
+        assert!(reply(deps.as_mut(), mock_env(), instantiate_reply(res.messages[0].id, None)).is_err());
+    }
 
-            } else {
-                // Burn received pusd and send stkn
-                let mut stkn_amount = wrapper.amount * Uint128::from(NORMAL_DECIMAL) / cfg.price ;
+    #[test]
+    fn adopted_pusd_and_staking_parameters_are_checked() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(mock_querier(None, Uint128::new(STOCK_PRICE)));
+        let adopt = |pusd: &str| InstantiateMsg {
+            pusd_address: Some(Addr::unchecked(pusd)),
+            staking_address: None,
+            ..instantiate_msg()
+        };
+        let failure = |err: ContractError| StdError::generic_err(err.to_string());
 
-                if cfg.price * expected_amount / Uint128::from(NORMAL_DECIMAL) == wrapper.amount {
-                    stkn_amount = expected_amount;
-                }
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), adopt("foreign_pusd")).unwrap_err();
+        assert_eq!(err, failure(ContractError::PusdMinterMismatch {}));
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), adopt("pusd18")).unwrap_err();
+        assert_eq!(err, failure(ContractError::InvalidPusdDecimals { expected: PUSD_DECIMALS, decimals: 18 }));
+        let mut msg = adopt("pusd");
+        msg.staking.ratios = vec![];
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+        assert_eq!(err, failure(ContractError::InvalidInput {}));
 
-                if stkn_amount > util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())? {
-                    return Err(ContractError::InsufficientStkn {});
-                }
-                
-                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: cfg.pusd_address.clone().into(),
-                    funds: vec![],
-                    msg: to_binary(&Cw20ExecuteMsg::Burn {
-                        amount: wrapper.amount
-                    })?,
-                }));
-                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: cfg.stkn_address.clone().into(),
-                    funds: vec![],
-                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                        recipient: user_addr.clone().into(),
-                        amount: stkn_amount
-                    })?,
-                }));
-                return Ok(Response::new()
-                    .add_messages(messages)
-                    .add_attributes(vec![
-                        attr("action", "buy_stkn"),
-                        attr("address", user_addr),
-                        attr("amount", stkn_amount)
-                ]));
-            }
+        // only staking is left to bootstrap, with the given lock tiers
+        let res = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), adopt("pusd")).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, INSTANTIATE_STAKING_ID);
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, msg, .. }) => {
+                let msg: serde_json::Value = serde_json::from_slice(msg.as_slice()).unwrap();
+                assert_eq!(*code_id, 4);
+                assert_eq!(msg["lock_days"], serde_json::json!([30]));
+                assert_eq!(msg["ratios"], serde_json::json!([20000]));
+            },
+            msg => panic!("unexpected message {:?}", msg),
         }
+        assert_eq!(query_bootstrap_status(deps.as_ref()).unwrap().pusd_address, Some(Addr::unchecked("pusd")));
     }
-    
-}
 
-pub fn check_stock_subcontract(
-    storage: &mut dyn Storage,
-    id: u32,
-    address: Addr
-) -> Result<Response, ContractError> {
-    let config = CONFIG.load(storage)?;
-    if config.owner == address || config.staking_address == address || config.stkn_address == address || config.pusd_address == address {
-        return Ok(Response::new().add_attribute("action", "check_stock_contracts"));
+    #[test]
+    fn subcontract_roles_come_from_the_address_index() {
+        let mut deps = setup();
+        add_stock(&mut deps, 2, "msft");
+        let role = |address: &str| query_subcontract_role(deps.as_ref(), Addr::unchecked(address)).unwrap();
+
+        let owner = role(OWNER);
+        assert!(owner.is_owner);
+        assert_eq!((owner.stock_id, owner.role), (None, None));
+        let trading = role("aapl_trading");
+        assert_eq!((trading.stock_id, trading.role), (Some(1), Some(ContractRole::Trading)));
+        assert_eq!(trading.reason, "trading contract of stock 1, authorized for this stock only");
+        let staking = role("staking");
+        assert_eq!((staking.stock_id, staking.role), (None, Some(ContractRole::Staking)));
+        let user = role("user");
+        assert_eq!((user.is_owner, user.role), (false, None));
+        assert_eq!(user.reason, "not registered, not authorized");
+
+        // subcontracts only act for their own stock, protocol contracts for every stock
+        assert!(query_check_stock_subcontract(deps.as_ref(), 1, Addr::unchecked("aapl_pool")).unwrap());
+        assert!(!query_check_stock_subcontract(deps.as_ref(), 2, Addr::unchecked("aapl_pool")).unwrap());
+        assert!(query_check_stock_subcontract(deps.as_ref(), 2, Addr::unchecked("staking")).unwrap());
+        let mint = ExecuteMsg::MintPusd { id: 2, recipient: Addr::unchecked("user"), amount: Uint128::new(100) };
+        assert_eq!(exec(&mut deps, "aapl_pool", mint.clone()).unwrap_err(), ContractError::Unauthorized {});
+        exec(&mut deps, "msft_pool", mint).unwrap();
     }
-    let stock_info = STOCKS.load(storage, id)?;
-    
-    if stock_info.providing_address == address || stock_info.trading_address == address || stock_info.shorting_address == address || stock_info.pool_address == address || stock_info.stock_address == address {
-        Ok(Response::new().add_attribute("action", "check_stock_contracts"))
-    } else {
-        return Err(ContractError::Unauthorized {})
+
+    #[test]
+    fn delisted_stock_is_redeemed_and_archived() {
+        let mut deps = setup();
+        add_stock(&mut deps, 2, "msft");
+        let redeem = ReceiveMsg::RedeemDelisted { recipient: None };
+
+        let err = exec(&mut deps, "user", ExecuteMsg::DelistStock { id: 1, redemption_period: 1000 }).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Lister.as_str().to_string() });
+        let res = exec(&mut deps, OWNER, ExecuteMsg::DelistStock { id: 1, redemption_period: 1000 }).unwrap();
+        let withdraw_only = |address: &str| CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            funds: vec![],
+            msg: to_binary(&SubcontractExecuteMsg::SetWithdrawOnly {}).unwrap()
+        });
+        assert_eq!(res.messages.iter().map(|message| message.msg.clone()).collect::<Vec<_>>(), vec![
+            withdraw_only("aapl_pool"),
+            withdraw_only("aapl_trading")
+        ]);
+        assert_eq!(STOCKS.load(&deps.storage, 1).unwrap().status, StockStatus::Delisted);
+        let err = exec(&mut deps, OWNER, ExecuteMsg::DelistStock { id: 1, redemption_period: 1000 }).unwrap_err();
+        assert_eq!(err, ContractError::StockDelisted { id: 1 });
+
+        // redemptions stay open while the manager is disabled
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateEnabled { enabled: false }).unwrap();
+        let res = exec(&mut deps, "aapl", receive("holder", 100_000_000, &redeem)).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.attributes[0], attr("action", "redeem_delisted"));
+        assert_eq!(DELISTINGS.load(&deps.storage, 1).unwrap().redeemed, Uint128::new(100_000_000));
+        let err = exec(&mut deps, "msft", receive("holder", 100, &redeem)).unwrap_err();
+        assert_eq!(err, ContractError::StockNotDelisted { id: 2 });
+        let err = exec(&mut deps, "aapl", receive("holder", STOCK_SUPPLY, &redeem)).unwrap_err();
+        assert_eq!(err, ContractError::RedemptionCapExceeded { id: 1 });
+
+        let err = exec(&mut deps, OWNER, ExecuteMsg::ArchiveStock { id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::RedemptionOpen { id: 1 });
+        let err = exec(&mut deps, OWNER, ExecuteMsg::ArchiveStock { id: 2 }).unwrap_err();
+        assert_eq!(err, ContractError::StockNotDelisted { id: 2 });
+        let err = exec_at(&mut deps, later(1001), "aapl", receive("holder", 100, &redeem)).unwrap_err();
+        assert_eq!(err, ContractError::RedemptionClosed { id: 1 });
+
+        exec_at(&mut deps, later(1001), OWNER, ExecuteMsg::ArchiveStock { id: 1 }).unwrap();
+        assert!(!STOCKS.has(&deps.storage, 1));
+        assert!(!STOCK_SYMBOLS.has(&deps.storage, "AAPL"));
+        assert_eq!(query_stock_count(deps.as_ref()).unwrap().count, 1);
+        assert_eq!(query_subcontract_role(deps.as_ref(), Addr::unchecked("aapl_pool")).unwrap().role, None);
+        let archived = query_archived_stocks(deps.as_ref(), None, None).unwrap().list;
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].stock.id, 1);
+        assert_eq!(archived[0].delisting.redeemed, Uint128::new(100_000_000));
     }
-}
 
-pub fn execute_mint_pusd(
-    deps: DepsMut,
-    env: Env,
-    caller: Addr,
-    id: u32,
-    recipient: Addr,
-    amount: Uint128
-) -> Result<Response, ContractError> {
+    #[test]
+    fn timelocked_operations_wait_for_the_current_delay() {
+        let mut deps = setup();
+        let update_fees = ExecuteMsg::UpdateFees {
+            buy_pusd_fee_bps: 50,
+            sell_pusd_fee_bps: 50,
+            fee_collector: Addr::unchecked(OWNER)
+        };
+        let delay = |delay: u64| ExecuteMsg::UpdateTimelockDelay { category: TimelockCategory::Fees, delay };
+        exec(&mut deps, OWNER, delay(3600)).unwrap();
 
-    util::check_enabled(deps.querier, env.contract.address.clone())?;
-    check_stock_subcontract(deps.storage, id, caller.clone())?;
-    
-    let mut messages:Vec<CosmosMsg> = vec![];
+        let err = exec(&mut deps, OWNER, update_fees.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Timelocked { category: "fees".to_string() });
 
-    let cfg = CONFIG.load(deps.storage)?;
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: cfg.pusd_address.clone().into(),
-        funds: vec![],
-        msg: to_binary(&Cw20ExecuteMsg::Mint {
-            recipient: recipient.clone().into(),
-            amount
-        })?,
-    }));
+        let now = mock_env().block.time.seconds();
+        let schedule = |eta: u64| ExecuteMsg::Schedule { msg: Box::new(update_fees.clone()), eta };
+        let err = exec(&mut deps, OWNER, schedule(now + 100)).unwrap_err();
+        assert_eq!(err, ContractError::EtaTooEarly { min_eta: now + 3600 });
+        let err = exec(&mut deps, "user", schedule(now + 3600)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        exec(&mut deps, OWNER, schedule(now + 3600)).unwrap();
 
-    return Ok(Response::new()
-        .add_messages(messages)
-        .add_attributes(vec![
-            attr("action", "mint_pusd"),
-            attr("id", id.to_string()),
-            attr("address", recipient.clone()),
-            attr("amount", amount)
-    ]));
-}
+        let err = exec(&mut deps, "keeper", ExecuteMsg::Execute { id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::OperationNotReady { eta: now + 3600 });
+        exec_at(&mut deps, later(3600), "keeper", ExecuteMsg::Execute { id: 1 }).unwrap();
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().buy_pusd_fee_bps, 50);
 
+        // a delay raised after scheduling holds the operation back as well
+        exec(&mut deps, OWNER, schedule(now + 3600)).unwrap();
+        exec(&mut deps, OWNER, delay(7200)).unwrap();
+        let err = exec_at(&mut deps, later(3600), "keeper", ExecuteMsg::Execute { id: 2 }).unwrap_err();
+        assert_eq!(err, ContractError::OperationNotReady { eta: now + 7200 });
+        exec_at(&mut deps, later(7200), "keeper", ExecuteMsg::Execute { id: 2 }).unwrap();
+    }
 
-pub fn execute_mint_stock(
-    deps: DepsMut,
-    env: Env,
-    caller: Addr,
-    id: u32,
-    recipient: Addr,
-    amount: Uint128
-) -> Result<Response, ContractError> {
+    #[test]
+    fn multisig_proposals_count_current_members() {
+        let mut deps = setup();
+        let members = vec![Addr::unchecked("alice"), Addr::unchecked("bob"), Addr::unchecked("carol")];
+        exec(&mut deps, OWNER, ExecuteMsg::SetupMultisig { members, threshold: 2, voting_period: 1000 }).unwrap();
 
-    util::check_enabled(deps.querier, env.contract.address.clone())?;
-    check_stock_subcontract(deps.storage, id, caller.clone())?;
-    
-    let stock_info = STOCKS.load(deps.storage, id)?;
+        let propose = |target: Option<&str>| ExecuteMsg::Propose {
+            title: String::from("pause"),
+            target: target.map(Addr::unchecked),
+            msg: to_binary(&ExecuteMsg::UpdateEnabled { enabled: false }).unwrap()
+        };
+        assert_eq!(exec(&mut deps, "alice", propose(Some("stkn"))).unwrap_err(), ContractError::InvalidInput {});
+        assert_eq!(exec(&mut deps, "dave", propose(None)).unwrap_err(), ContractError::NotMember {});
+        exec(&mut deps, "alice", propose(Some("aapl_pool"))).unwrap();
+        exec(&mut deps, "alice", propose(None)).unwrap();
 
-    let mut messages:Vec<CosmosMsg> = vec![];
+        let err = exec(&mut deps, "bob", ExecuteMsg::ExecuteProposal { id: 2 }).unwrap_err();
+        assert_eq!(err, ContractError::ProposalNotPassed {});
+        let err = exec(&mut deps, "alice", ExecuteMsg::Vote { id: 2, yes: true }).unwrap_err();
+        assert_eq!(err, ContractError::AlreadyVoted {});
+        exec(&mut deps, "bob", ExecuteMsg::Vote { id: 1, yes: true }).unwrap();
+        exec(&mut deps, "bob", ExecuteMsg::Vote { id: 2, yes: true }).unwrap();
+        assert_eq!(PROPOSALS.load(&deps.storage, 2).unwrap().status, ProposalStatus::Passed);
 
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: stock_info.stock_address.clone().into(),
-        funds: vec![],
-        msg: to_binary(&Cw20ExecuteMsg::Mint {
-            recipient: recipient.clone().into(),
-            amount
-        })?,
-    }));
+        let res = exec(&mut deps, "carol", ExecuteMsg::ExecuteProposal { id: 1 }).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(PROPOSALS.load(&deps.storage, 1).unwrap().status, ProposalStatus::Executed);
 
-    return Ok(Response::new()
-        .add_messages(messages)
-        .add_attributes(vec![
-            attr("action", "mint_stock"),
-            attr("id", id.to_string()),
-            attr("address", recipient.clone()),
-            attr("amount", amount)
-    ]));
-}
+        // once bob is removed his vote no longer passes proposal 2
+        let update_multisig = |remove: Vec<Addr>, threshold: Option<u32>| ExecuteMsg::UpdateMultisig {
+            add: vec![],
+            remove,
+            threshold,
+            voting_period: None
+        };
+        exec(&mut deps, MOCK_CONTRACT_ADDR, update_multisig(vec![Addr::unchecked("bob")], None)).unwrap();
+        let err = exec(&mut deps, "carol", ExecuteMsg::ExecuteProposal { id: 2 }).unwrap_err();
+        assert_eq!(err, ContractError::ProposalNotPassed {});
 
-pub fn execute_transfer_stkn(
-    deps: DepsMut,
-    env: Env,
-    caller: Addr,
-    id: u32,
-    recipient: Addr,
-    amount: Uint128
-) -> Result<Response, ContractError> {
+        // the current threshold applies as well
+        exec(&mut deps, MOCK_CONTRACT_ADDR, update_multisig(vec![], Some(1))).unwrap();
+        exec(&mut deps, "carol", ExecuteMsg::ExecuteProposal { id: 2 }).unwrap();
+    }
 
-    util::check_enabled(deps.querier, env.contract.address.clone())?;
-    check_stock_subcontract(deps.storage, id, caller.clone())?;
+    #[test]
+    fn renounced_ownership_authorizes_nobody() {
+        let mut deps = setup();
+        exec(&mut deps, OWNER, ExecuteMsg::GrantRole { role: Role::Pauser, address: Addr::unchecked("pauser") }).unwrap();
+        // the multisig acts as owner while the manager owns itself
+        exec(&mut deps, OWNER, ExecuteMsg::ProposeOwner { owner: Addr::unchecked(MOCK_CONTRACT_ADDR), expiry: None }).unwrap();
+        exec(&mut deps, MOCK_CONTRACT_ADDR, ExecuteMsg::AcceptOwnership {}).unwrap();
+        exec(&mut deps, MOCK_CONTRACT_ADDR, ExecuteMsg::ProposeOwner { owner: Addr::unchecked(OWNER), expiry: None }).unwrap();
 
-    let cfg = CONFIG.load(deps.storage)?;
+        exec(&mut deps, MOCK_CONTRACT_ADDR, ExecuteMsg::RenounceOwnership {}).unwrap();
+        assert_eq!(CONFIG.load(&deps.storage).unwrap().owner, None);
+        assert_eq!(query_config(deps.as_ref(), mock_env()).unwrap().owner, None);
+        assert_eq!(exec(&mut deps, OWNER, ExecuteMsg::AcceptOwnership {}).unwrap_err(), ContractError::NoPendingOwner {});
 
-    if util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone())? < amount {
-        return Err(ContractError::NotEnoughStkn {});
+        let update_fees = ExecuteMsg::UpdateFees {
+            buy_pusd_fee_bps: 50,
+            sell_pusd_fee_bps: 50,
+            fee_collector: Addr::unchecked(OWNER)
+        };
+        for sender in [OWNER, MOCK_CONTRACT_ADDR] {
+            assert_eq!(exec(&mut deps, sender, update_fees.clone()).unwrap_err(), ContractError::Unauthorized {});
+            let err = exec(&mut deps, sender, ExecuteMsg::ProposeOwner { owner: Addr::unchecked(sender), expiry: None }).unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            let err = exec(&mut deps, sender, ExecuteMsg::UpdateEnabled { enabled: false }).unwrap_err();
+            assert_eq!(err, ContractError::MissingRole { role: Role::Pauser.as_str().to_string() });
+        }
+        assert!(!query_subcontract_role(deps.as_ref(), Addr::unchecked(MOCK_CONTRACT_ADDR)).unwrap().is_owner);
+        exec(&mut deps, "pauser", ExecuteMsg::UpdateEnabled { enabled: false }).unwrap();
     }
-    
-    let mut messages:Vec<CosmosMsg> = vec![];
 
-    let cfg = CONFIG.load(deps.storage)?;
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: cfg.stkn_address.clone().into(),
-        funds: vec![],
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: recipient.clone().into(),
-            amount
-        })?,
-    }));
+    #[test]
+    fn sync_due_pays_the_keeper_for_recorded_syncs() {
+        let mut deps = setup();
+        add_stock(&mut deps, 2, "msft");
+        add_stock(&mut deps, 3, "tsla");
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateStockStatus { id: 3, status: StockStatus::Paused }).unwrap();
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateKeeperReward { amount: Uint128::new(1000) }).unwrap();
 
-    
-    return Ok(Response::new()
-        .add_messages(messages)
-        .add_attributes(vec![
-            attr("action", "send_stkn"),
-            attr("id", id.to_string()),
-            attr("address", recipient.clone()),
-            attr("amount", amount)
-    ]));
-    
+        // paused stocks are not synced
+        let res = exec(&mut deps, "keeper", ExecuteMsg::SyncDue {}).unwrap();
+        assert_eq!(res.attributes[1], attr("ids", "1,2"));
+        assert_eq!(res.messages.len(), 3);
+        assert_eq!(res.messages[0].id, SYNC_REPLY_ID + 1);
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Error);
+        assert_eq!(res.messages[2].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            funds: vec![],
+            msg: to_binary(&ExecuteMsg::PayKeeperReward {}).unwrap()
+        }));
 
-}
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, env: Env, msg: ManagerQueryMsg) -> StdResult<Binary> {
-    match msg {
-        ManagerQueryMsg::Config {} 
-            => to_binary(&query_config(deps, env)?),
-        ManagerQueryMsg::Stock {id} 
-            => to_binary(&query_stock(deps, id)?),
-        ManagerQueryMsg::ListStocks {} 
-            => to_binary(&query_list_stocks(deps)?),
-        ManagerQueryMsg::CheckStockSubcontract {id, address} 
-            => to_binary(&query_check_stock_subcontract(deps, id, address)?)
+        // only pools record syncs, the failed one is skipped
+        for sender in ["user", "aapl"] {
+            assert_eq!(exec(&mut deps, sender, ExecuteMsg::RecordSync {}).unwrap_err(), ContractError::Unauthorized {});
+        }
+        exec(&mut deps, "aapl_pool", ExecuteMsg::RecordSync {}).unwrap();
+        let res = reply(deps.as_mut(), mock_env(), Reply { id: SYNC_REPLY_ID + 2, result: SubMsgResult::Err(String::from("stale price")) }).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "sync_failed"));
+
+        let err = exec(&mut deps, "keeper", ExecuteMsg::PayKeeperReward {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = exec(&mut deps, MOCK_CONTRACT_ADDR, ExecuteMsg::PayKeeperReward {}).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("stkn"),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient: String::from("keeper"), amount: Uint128::new(1000) }).unwrap()
+        }));
+        assert!(!KEEPER_RUN.has(&deps.storage));
+
+        // the pool that failed stays due, the synced one waits for the interval
+        let res = exec(&mut deps, "keeper", ExecuteMsg::SyncDue {}).unwrap();
+        assert_eq!(res.attributes[1], attr("ids", "2"));
+        exec(&mut deps, "msft_pool", ExecuteMsg::RecordSync {}).unwrap();
+        assert_eq!(exec(&mut deps, "keeper", ExecuteMsg::SyncDue {}).unwrap_err(), ContractError::NothingToSync {});
+        let due = query_due_for_sync(deps.as_ref(), later(600), None).unwrap();
+        assert_eq!(due.stocks.iter().map(|stock| stock.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(due.reward, Uint128::new(2000));
+
+        // no reward when it would cut into the minimum reserve
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateMinStknReserve { amount: Uint128::new(STKN_BALANCE) }).unwrap();
+        exec_at(&mut deps, later(600), "keeper", ExecuteMsg::SyncDue {}).unwrap();
+        exec_at(&mut deps, later(600), "aapl_pool", ExecuteMsg::RecordSync {}).unwrap();
+        let res = exec_at(&mut deps, later(600), MOCK_CONTRACT_ADDR, ExecuteMsg::PayKeeperReward {}).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[4], attr("reward", "0"));
     }
-}
 
-pub fn query_config(deps: Deps, env: Env) -> StdResult<ManagerConfigResponse> {
-    let cfg = CONFIG.load(deps.storage)?;
-    Ok(ManagerConfigResponse {
-        owner: cfg.owner,
-        stkn_address: cfg.stkn_address.clone(),
-        pusd_address: cfg.pusd_address,
-        staking_address: cfg.staking_address,
-        
-        cw20_code_id: cfg.cw20_code_id,
-        stock_code_id: cfg.stock_code_id,
-        pool_code_id: cfg.pool_code_id,
+    #[test]
+    fn circuit_breaker_rejects_the_mint_passing_the_volume() {
+        let mut deps = setup();
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateCircuitBreaker {
+            id: 1,
+            breaker: Some(CircuitBreaker {
+                window: 1000,
+                max_pusd_minted: Some(Uint128::new(100)),
+                max_stock_minted: None,
+                max_price_change_bps: None
+            })
+        }).unwrap();
+        let mint = |amount: u128| ExecuteMsg::MintPusd { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(amount) };
 
-        staking_code_id: cfg.staking_code_id,
+        // mints older than the window no longer count
+        exec(&mut deps, "aapl_pool", mint(60)).unwrap();
+        exec_at(&mut deps, later(1100), "aapl_pool", mint(60)).unwrap();
 
-        shorting_code_id: cfg.shorting_code_id,
-        trading_code_id: cfg.trading_code_id,
-        providing_code_id: cfg.providing_code_id,
+        let err = exec_at(&mut deps, later(1100), "aapl_pool", mint(60)).unwrap_err();
+        assert_eq!(err, ContractError::CircuitBreakerLimit { id: 1, reason: String::from("pusd_volume") });
+        assert_eq!(STOCKS.load(&deps.storage, 1).unwrap().status, StockStatus::Active);
+        assert!(!BREAKER_TRIPS.has(&deps.storage, 1));
 
-        price: cfg.price,
-        stkn_amount: util::get_token_amount(deps.querier, Denom::Cw20(cfg.stkn_address.clone()), env.contract.address.clone()).unwrap(),
-        max_stock_id: cfg.max_stock_id,
-        enabled: cfg.enabled,
-        providing_sync_interval: cfg.providing_sync_interval
-    })
-}
+        // the rejected mint is not counted
+        let res = exec_at(&mut deps, later(1100), "aapl_pool", mint(40)).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert!(res.events.is_empty());
+    }
 
-pub fn query_stock(deps: Deps, id: u32) -> StdResult<StockInfo> {
-    
-    let stock_info = STOCKS.load(deps.storage, id)?;
-    Ok(stock_info)
-}
+    #[test]
+    fn circuit_breaker_trips_on_price_without_mint() {
+        let mut deps = setup();
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateCircuitBreaker {
+            id: 1,
+            breaker: Some(CircuitBreaker {
+                window: 1000,
+                max_pusd_minted: None,
+                max_stock_minted: None,
+                max_price_change_bps: Some(1000)
+            })
+        }).unwrap();
+        let mint = || ExecuteMsg::MintPusd { id: 1, recipient: Addr::unchecked("user"), amount: Uint128::new(10) };
 
-pub fn query_check_stock_subcontract(deps: Deps, id: u32, address: Addr) -> StdResult<bool> {
-    
-    let stock_info = STOCKS.load(deps.storage, id)?;
-    let cfg = CONFIG.load(deps.storage)?;
-    if cfg.owner == address || stock_info.providing_address == address || stock_info.trading_address == address || stock_info.shorting_address == address || stock_info.pool_address == address {
-        Ok(true)
-    } else {
-        return Ok(false)
+        let res = exec(&mut deps, "anyone", ExecuteMsg::CheckCircuitBreaker { id: 1 }).unwrap();
+        assert_eq!(res.attributes[2], attr("tripped", "false"));
+        exec(&mut deps, "aapl_pool", mint()).unwrap();
+
+        // a mint at the moved price is rejected, the stock is only paused by a check without mint
+        refresh_querier(&mut deps, Uint128::new(STOCK_PRICE * 12 / 10));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("aapl_pool", &[]), mint()).unwrap_err();
+        assert_eq!(err, ContractError::CircuitBreakerLimit { id: 1, reason: String::from("price_change") });
+        assert_eq!(STOCKS.load(&deps.storage, 1).unwrap().status, StockStatus::Active);
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::CheckCircuitBreaker { id: 1 }).unwrap();
+        assert_eq!(res.attributes[2], attr("tripped", "true"));
+        assert_eq!(res.events[0].ty, "circuit_breaker_tripped");
+        let trip = BREAKER_TRIPS.load(&deps.storage, 1).unwrap();
+        assert_eq!(trip.reason, "price_change");
+        assert_eq!(trip.previous_status, StockStatus::Active);
+        let err = exec(&mut deps, "aapl_pool", mint()).unwrap_err();
+        assert_eq!(err, ContractError::StockNotActive { id: 1, status: String::from("paused") });
+
+        let err = exec(&mut deps, "user", ExecuteMsg::Resume { id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Guardian.as_str().to_string() });
+        exec(&mut deps, OWNER, ExecuteMsg::Resume { id: 1 }).unwrap();
+        assert_eq!(STOCKS.load(&deps.storage, 1).unwrap().status, StockStatus::Active);
+        exec(&mut deps, "aapl_pool", mint()).unwrap();
     }
-}
 
-pub fn query_list_stocks(deps: Deps) 
--> StdResult<StockListResponse> {
-    let stocks:StdResult<Vec<_>> = STOCKS
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|item| map_stock(item))
-        .collect();
+    #[test]
+    fn price_change_saturates() {
+        let prices = PriceWindow { buckets: vec![(0, Uint128::new(1), Uint128::new(1))] };
+        assert_eq!(price_change_bps(&prices, Uint128::new(1 << 70)), u64::MAX);
+        assert_eq!(price_change_bps(&prices, Uint128::new(1)), 0);
+        assert_eq!(price_change_bps(&PriceWindow::default(), Uint128::new(5)), 0);
+    }
 
-    Ok(StockListResponse {
-        list: stocks?
-    })
-}
+    #[test]
+    fn stock_metadata_is_pushed_only_as_marketing_address() {
+        let mut deps = setup();
+        add_stock(&mut deps, 2, "legacy");
+        let metadata = StockMetadata {
+            exchange: Some(String::from("NASDAQ")),
+            isin: Some(String::from("US0378331005")),
+            ..StockMetadata::default()
+        };
 
-fn map_stock(
-    item: StdResult<(u32, StockInfo)>,
-) -> StdResult<StockInfo> {
-    item.map(|(_id, stock_info)| {
-        stock_info
-    })
-}
+        let err = exec(&mut deps, "user", ExecuteMsg::UpdateStockMetadata { id: 1, metadata: metadata.clone() }).unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Lister.as_str().to_string() });
+        let err = exec(&mut deps, OWNER, ExecuteMsg::UpdateStockMetadata {
+            id: 1,
+            metadata: StockMetadata { cusip: Some(String::from("03783310")), ..metadata.clone() }
+        }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidInput {});
 
+        let res = exec(&mut deps, OWNER, ExecuteMsg::UpdateStockMetadata { id: 1, metadata: metadata.clone() }).unwrap();
+        assert_eq!(res.attributes[2], attr("pushed", "true"));
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("aapl"),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::UpdateMarketing {
+                project: None,
+                description: Some(String::from("NASDAQ | ISIN US0378331005")),
+                marketing: None
+            }).unwrap()
+        }));
 
+        // the owner is the marketing address of the token, the metadata is only stored
+        let res = exec(&mut deps, OWNER, ExecuteMsg::UpdateStockMetadata { id: 2, metadata: metadata.clone() }).unwrap();
+        assert_eq!(res.attributes[2], attr("pushed", "false"));
+        assert!(res.messages.is_empty());
+        assert_eq!(STOCKS.load(&deps.storage, 2).unwrap().metadata, metadata);
+    }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    let version = get_contract_version(deps.storage)?;
-    if version.contract != CONTRACT_NAME {
-        return Err(ContractError::CannotMigrate {
-            previous_contract: version.contract,
+    #[test]
+    fn token_logos_are_checked_before_forwarding() {
+        let mut deps = setup();
+        let svg = |logo: &[u8]| Logo::Embedded(EmbeddedLogo::Svg(Binary::from(logo)));
+        let png = |logo: Vec<u8>| Logo::Embedded(EmbeddedLogo::Png(Binary::from(logo)));
+        let header = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+        assert!(manager::verify_logo(&svg(br#"<?xml version="1.0"?><svg/>"#)).is_ok());
+        assert!(manager::verify_logo(&png(header.clone())).is_ok());
+        assert!(manager::verify_logo(&Logo::Url(String::from("https://example.com/logo.png"))).is_ok());
+        let invalid = |reason: &str| Err(ContractError::InvalidLogo { reason: reason.to_string() });
+        assert_eq!(manager::verify_logo(&svg(b"<svg/>")), invalid("Invalid xml preamble for SVG"));
+        let mut large = br#"<?xml version="1.0"?>"#.to_vec();
+        large.resize(5 * 1024 + 1, b' ');
+        assert_eq!(manager::verify_logo(&svg(&large)), invalid("Logo binary data exceeds 5KB limit"));
+        assert_eq!(manager::verify_logo(&png(b"GIF89a".to_vec())), invalid("Invalid png header"));
+
+        let err = exec(&mut deps, "user", ExecuteMsg::UploadTokenLogo { token: MarketingToken::Pusd, logo: png(header.clone()) }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = exec(&mut deps, OWNER, ExecuteMsg::UploadTokenLogo { token: MarketingToken::Pusd, logo: svg(b"<svg/>") }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidLogo { reason: String::from("Invalid xml preamble for SVG") });
+        let res = exec(&mut deps, OWNER, ExecuteMsg::UploadTokenLogo { token: MarketingToken::Stock { id: 1 }, logo: png(header.clone()) }).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("aapl"),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::UploadLogo(png(header))).unwrap()
+        }));
+
+        // stock tokens keep the manager as marketing address, PUSD can hand it over
+        let marketing = |token: MarketingToken| ExecuteMsg::UpdateTokenMarketing {
+            token,
+            project: None,
+            description: None,
+            marketing: Some(String::from("marketing"))
+        };
+        let err = exec(&mut deps, OWNER, marketing(MarketingToken::Stock { id: 1 })).unwrap_err();
+        assert_eq!(err, ContractError::StockMarketingFixed {});
+        let res = exec(&mut deps, OWNER, marketing(MarketingToken::Pusd)).unwrap();
+        assert_eq!(res.attributes[1], attr("token", "pusd"));
+    }
+
+    #[test]
+    fn split_rescales_stock_limits_and_accounting() {
+        let mut deps = setup();
+        let split = |numerator: u64, denominator: u64| ExecuteMsg::CorporateAction(CorporateAction::Split {
+            id: 1,
+            numerator,
+            denominator
         });
+        let caller = Addr::unchecked("aapl_pool");
+        MINT_LIMITS.save(&mut deps.storage, (1, MintKind::Stock.as_str()), &MintLimit {
+            ceiling: Some(Uint128::new(1000)),
+            window: 1000,
+            window_limit: Some(Uint128::new(500))
+        }).unwrap();
+        record_mint(&mut deps.storage, &mock_env(), 1, MintKind::Stock, &caller, Uint128::new(200)).unwrap();
+        CIRCUIT_BREAKERS.save(&mut deps.storage, 1, &CircuitBreaker {
+            window: 1000,
+            max_pusd_minted: Some(Uint128::new(700)),
+            max_stock_minted: Some(Uint128::new(300)),
+            max_price_change_bps: None
+        }).unwrap();
+
+        let err = exec(&mut deps, OWNER, split(2, 1)).unwrap_err();
+        assert_eq!(err, ContractError::StockNotPaused { id: 1 });
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateStockStatus { id: 1, status: StockStatus::Paused }).unwrap();
+        let err = exec(&mut deps, OWNER, split(2, 2)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidInput {});
+        let err = exec(&mut deps, "user", split(2, 1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let res = exec(&mut deps, OWNER, split(2, 1)).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("aapl_pool"),
+            funds: vec![],
+            msg: to_binary(&SubcontractExecuteMsg::Split { numerator: 2, denominator: 1 }).unwrap()
+        }));
+
+        let limit = MINT_LIMITS.load(&deps.storage, (1, MintKind::Stock.as_str())).unwrap();
+        assert_eq!(limit.ceiling, Some(Uint128::new(2000)));
+        assert_eq!(limit.window_limit, Some(Uint128::new(1000)));
+        let usage = query_mint_usage(deps.as_ref(), mock_env(), 1, MintKind::Stock).unwrap();
+        assert_eq!(usage.outstanding, Uint128::new(400));
+        assert_eq!(usage.window_minted, Uint128::new(400));
+        assert_eq!(query_caller_minted(deps.as_ref(), 1, MintKind::Stock, caller).unwrap(), Uint128::new(400));
+        let breaker = CIRCUIT_BREAKERS.load(&deps.storage, 1).unwrap();
+        assert_eq!(breaker.max_stock_minted, Some(Uint128::new(600)));
+        assert_eq!(breaker.max_pusd_minted, Some(Uint128::new(700)));
+        assert_eq!(CORPORATE_ACTION_COUNT.load(&deps.storage).unwrap(), 1);
     }
-    Ok(Response::default())
-}
 
+    #[test]
+    fn split_waits_for_open_positions() {
+        let mut deps = setup();
+        add_stock(&mut deps, 2, "busy");
+        exec(&mut deps, OWNER, ExecuteMsg::UpdateStockStatus { id: 2, status: StockStatus::Paused }).unwrap();
+
+        let err = exec(&mut deps, OWNER, ExecuteMsg::CorporateAction(CorporateAction::Split {
+            id: 2,
+            numerator: 2,
+            denominator: 1
+        })).unwrap_err();
+        assert_eq!(err, ContractError::OpenPositions { id: 2, count: 2 });
+        assert_eq!(CORPORATE_ACTION_COUNT.load(&deps.storage).unwrap(), 0);
+    }
+}
@@ -1,4 +1,5 @@
-use cosmwasm_std::{StdError};
+use cosmwasm_std::{StdError, Uint128};
+use cw_utils::ParseReplyError;
 use hex::FromHexError;
 use thiserror::Error;
 
@@ -10,9 +11,42 @@ pub enum ContractError {
     #[error("{0}")]
     Hex(#[from] FromHexError),
 
+    #[error("{0}")]
+    ParseReply(#[from] ParseReplyError),
+
+    #[error("Bootstrap is not finished")]
+    BootstrapNotDone {},
+
+    #[error("Bootstrap is already finished")]
+    BootstrapDone {},
+
+    #[error("PUSD logo url is missing, set it with MigrateMsg pusd_url")]
+    MissingPusdUrl {},
+
+    #[error("Manager has to be the minter of the adopted PUSD")]
+    PusdMinterMismatch {},
+
+    #[error("PUSD needs {expected} decimals, not {decimals}")]
+    InvalidPusdDecimals { expected: u8, decimals: u8 },
+
     #[error("Disabled")]
     Disabled {},
 
+    #[error("Symbol {symbol} is already listed")]
+    DuplicateSymbol { symbol: String },
+
+    #[error("Symbol {symbol} has to be 3 to 12 letters or dashes")]
+    InvalidSymbol { symbol: String },
+
+    #[error("Mint ceiling of stock {id} exceeded")]
+    MintCeilingExceeded { id: u32 },
+
+    #[error("Mint rate limit of stock {id} exceeded")]
+    MintRateLimitExceeded { id: u32 },
+
+    #[error("Stock {id} is {status}")]
+    StockNotActive { id: u32, status: String },
+
     // #[error("PoolAndTokenMismatch")]
     // PoolAndTokenMismatch {},
 
@@ -33,6 +67,36 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Missing role: {role}")]
+    MissingRole { role: String },
+
+    #[error("No pending owner")]
+    NoPendingOwner {},
+
+    #[error("Ownership proposal expired")]
+    OwnershipProposalExpired {},
+
+    #[error("Ownership was renounced")]
+    OwnershipRenounced {},
+
+    #[error("Swap output {output} is below min_output {min_output}")]
+    SlippageExceeded { output: Uint128, min_output: Uint128 },
+
+    #[error("Price {price} is above max_price {max_price}")]
+    PriceTooHigh { price: Uint128, max_price: Uint128 },
+
+    #[error("Fee must not exceed {max_bps} bps")]
+    InvalidFee { max_bps: u64 },
+
+    #[error("Not enough accrued fees")]
+    InsufficientFees {},
+
+    #[error("Swap deadline expired")]
+    SwapExpired {},
+
+    #[error("Price is stale")]
+    StalePrice {},
+
     #[error("InvalidInput")]
     InvalidInput {},
 
@@ -45,11 +109,107 @@ pub enum ContractError {
     #[error("Not enough STKN")]
     NotEnoughStkn {},
 
+    #[error("Withdrawal would leave less than the minimum STKN reserve")]
+    BelowMinReserve {},
+
+    #[error("Collateral ratio would drop below the minimum")]
+    CollateralRatioTooLow {},
+
+    #[error("Global settlement is active")]
+    Settled {},
+
+    #[error("Global settlement is not active")]
+    NotSettled {},
+
+    #[error("Global settlement is still snapshotting stocks")]
+    SettlementIncomplete {},
+
+    #[error("Global settlement has snapshotted every stock")]
+    SettlementComplete {},
+
+    #[error("Stock {id} redemptions would exceed its snapshotted supply")]
+    RedemptionCapExceeded { id: u32 },
+
+    #[error("{category} operations have to be scheduled")]
+    Timelocked { category: String },
+
+    #[error("Message cannot be scheduled")]
+    NotTimelockable {},
+
+    #[error("Eta has to be at least {min_eta}")]
+    EtaTooEarly { min_eta: u64 },
+
+    #[error("Operation can be executed from {eta}")]
+    OperationNotReady { eta: u64 },
+
+    #[error("Circuit breaker of stock {id} is tripped")]
+    CircuitBreakerTripped { id: u32 },
+
+    #[error("Mint rejected by the circuit breaker of stock {id}: {reason}")]
+    CircuitBreakerLimit { id: u32, reason: String },
+
+    #[error("Stock {id} has to be paused")]
+    StockNotPaused { id: u32 },
+
+    #[error("Stock {id} still has {count} open positions")]
+    OpenPositions { id: u32, count: u64 },
+
+    #[error("Circuit breaker of stock {id} is not tripped")]
+    CircuitBreakerNotTripped { id: u32 },
+
+    #[error("Invalid logo: {reason}")]
+    InvalidLogo { reason: String },
+
+    #[error("Marketing address of stock tokens cannot be changed")]
+    StockMarketingFixed {},
+
+    #[error("No pool is due for sync")]
+    NothingToSync {},
+
+    #[error("Sync interval has to be at least {min} seconds")]
+    SyncIntervalTooShort { min: u64 },
+
+    #[error("Multisig is not set up")]
+    MultisigNotSet {},
+
+    #[error("Multisig is already set up, membership changes go through proposals")]
+    MultisigAlreadySet {},
+
+    #[error("Threshold has to be between 1 and {members}")]
+    InvalidThreshold { members: u32 },
+
+    #[error("Not a multisig member")]
+    NotMember {},
+
+    #[error("Already voted")]
+    AlreadyVoted {},
+
+    #[error("Proposal is {status}")]
+    ProposalNotOpen { status: String },
+
+    #[error("Proposal has not passed")]
+    ProposalNotPassed {},
+
+    #[error("Stock {id} is delisted")]
+    StockDelisted { id: u32 },
+
+    #[error("Stock {id} is not delisted")]
+    StockNotDelisted { id: u32 },
+
+    #[error("Redemption window of stock {id} is closed")]
+    RedemptionClosed { id: u32 },
+
+    #[error("Redemption window of stock {id} is still open")]
+    RedemptionOpen { id: u32 },
+
     #[error("Map2List failed")]
     Map2ListFailed {},
 
     #[error("Cannot migrate from different contract type: {previous_contract}")]
     CannotMigrate { previous_contract: String },
 
+    #[error("Manager is not the admin of {address}")]
+    NotAdmin { address: String },
+
     
 }
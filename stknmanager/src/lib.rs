@@ -1,6 +1,11 @@
 pub mod contract;
 mod error;
+pub mod manager;
+pub mod migration;
 pub mod msg;
 pub mod state;
 pub mod util;
+
+#[cfg(test)]
+mod multitest;
 pub use crate::error::ContractError;
@@ -0,0 +1,487 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::{
+    Addr, Decimal, QuerierWrapper, QueryRequest, StdResult, Uint128, WasmQuery, to_binary
+};
+use cw20::{EmbeddedLogo, Expiration, Logo};
+pub use stknoracle::{OracleQueryMsg, OraclePriceResponse};
+use crate::error::ContractError;
+use crate::state::{CorporateActionRecord, BreakerTrip, BreakerWindow, CircuitBreaker, SyncRecord, MultisigConfig, Proposal, ScheduledOperation, TimelockCategory, ArchivedStock, ContractRole, Delisting, Role, MintKind, MintLimit, ReserveRecord, Settlement, Subcontract, BootstrapStage};
+use crate::util::get_manager_config;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockInfo {
+    pub id: u32,
+    pub symbol: String,
+    pub stock_address: Addr,
+    pub pool_address: Addr,
+    pub shorting_address: Addr,
+    pub trading_address: Addr,
+    pub providing_address: Addr,
+    pub status: StockStatus,
+    pub metadata: StockMetadata
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct StockMetadata {
+    pub exchange: Option<String>,
+    pub isin: Option<String>,
+    pub cusip: Option<String>,
+    pub sector: Option<String>,
+    /// ISO 4217 code of the quote currency
+    pub currency: Option<String>,
+    pub description: Option<String>,
+    /// e.g. "09:30-16:00 America/New_York"
+    pub trading_hours: Option<String>
+}
+
+impl StockMetadata {
+    /// ISIN has 12 and CUSIP 9 alphanumeric characters
+    pub fn validate(&self) -> Result<(), ContractError> {
+        let is_code = |code: &Option<String>, len: usize| {
+            code.as_ref().map_or(true, |code| code.len() == len && code.chars().all(|c| c.is_ascii_alphanumeric()))
+        };
+        if !is_code(&self.isin, 12) || !is_code(&self.cusip, 9) {
+            return Err(ContractError::InvalidInput {});
+        }
+        Ok(())
+    }
+
+    /// Description pushed to the stock token marketing info
+    pub fn marketing_description(&self) -> Option<String> {
+        let parts: Vec<String> = vec![
+            self.description.clone(),
+            self.exchange.clone(),
+            self.isin.as_ref().map(|isin| format!("ISIN {}", isin)),
+            self.cusip.as_ref().map(|cusip| format!("CUSIP {}", cusip)),
+            self.sector.clone(),
+            self.currency.clone(),
+            self.trading_hours.clone(),
+        ].into_iter().flatten().collect();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" | "))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StockStatus {
+    /// Minting and STKN transfers are allowed
+    Active,
+    /// Nothing can be minted or transferred for this stock
+    Paused,
+    /// Positions can only be closed: no new stock tokens, PUSD and STKN can still be paid out
+    ReduceOnly,
+    /// Stock is no longer traded
+    Delisted
+}
+
+impl StockStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StockStatus::Active => "active",
+            StockStatus::Paused => "paused",
+            StockStatus::ReduceOnly => "reduce_only",
+            StockStatus::Delisted => "delisted",
+        }
+    }
+}
+
+/// Every manager query, the other contracts use the subset in util
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ManagerQueryMsg {
+    Config {},
+    Stock {
+        id: u32
+    },
+    ListStocks {
+        start_after: Option<u32>,
+        limit: Option<u32>,
+        status: Option<StockStatus>,
+        symbol_prefix: Option<String>
+    },
+    StockCount {},
+    StockBySymbol {
+        symbol: String
+    },
+    CheckStockSubcontract {
+        id: u32,
+        address: Addr
+    },
+    SubcontractRole {
+        address: Addr
+    },
+    Delisting {
+        id: u32
+    },
+    ArchivedStocks {
+        start_after: Option<u32>,
+        limit: Option<u32>
+    },
+    DueForSync {
+        limit: Option<u32>
+    },
+    CircuitBreaker {
+        id: u32
+    },
+    CorporateAction {
+        action_id: u64
+    },
+    /// Filtered by stock when id is set
+    CorporateActions {
+        id: Option<u32>,
+        start_after: Option<u64>,
+        limit: Option<u32>
+    },
+    TimelockDelays {},
+    Multisig {},
+    Proposal {
+        id: u64
+    },
+    ListProposals {
+        start_after: Option<u64>,
+        limit: Option<u32>
+    },
+    ProposalVote {
+        id: u64,
+        address: Addr
+    },
+    ScheduledOperation {
+        id: u64
+    },
+    ScheduledOperations {
+        start_after: Option<u64>,
+        limit: Option<u32>
+    },
+    BootstrapStatus {},
+    PendingOwner {},
+    Price {},
+    Settlement {},
+    SettledStock {
+        id: u32
+    },
+    Solvency {
+        start_after: Option<u32>,
+        limit: Option<u32>
+    },
+    StknFunded {
+        address: Addr
+    },
+    ReserveHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>
+    },
+    SimulateSwap {
+        offer_token: Addr,
+        offer_amount: Uint128
+    },
+    ReverseSimulateSwap {
+        ask_token: Addr,
+        ask_amount: Uint128
+    },
+    RoleMembers {
+        role: Role,
+        start_after: Option<String>,
+        limit: Option<u32>
+    },
+    HasRole {
+        role: Role,
+        address: Addr
+    },
+    StockStatus {
+        id: u32
+    },
+    StockCodeIds {
+        id: u32
+    },
+    MintUsage {
+        id: u32,
+        kind: MintKind
+    },
+    CallerMinted {
+        id: u32,
+        kind: MintKind,
+        address: Addr
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockListResponse {
+    pub list: Vec<StockInfo>,
+    /// Set when more stocks may match, pass it as start_after to continue
+    pub next_start_after: Option<u32>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelistingResponse {
+    pub delisting: Option<Delisting>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ArchivedStockListResponse {
+    pub list: Vec<ArchivedStock>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TimelockDelaysResponse {
+    pub delays: Vec<(TimelockCategory, u64)>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScheduledOperationsResponse {
+    pub operations: Vec<ScheduledOperation>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MultisigResponse {
+    pub config: Option<MultisigConfig>,
+    pub members: Vec<Addr>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposalListResponse {
+    /// Statuses take the voting period into account
+    pub proposals: Vec<Proposal>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposalVoteResponse {
+    /// None when the address has not voted
+    pub yes: Option<bool>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CorporateActionsResponse {
+    pub actions: Vec<CorporateActionRecord>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CircuitBreakerResponse {
+    pub id: u32,
+    pub breaker: Option<CircuitBreaker>,
+    pub window: Option<BreakerWindow>,
+    /// Set while the stock is paused by its circuit breaker
+    pub trip: Option<BreakerTrip>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DueStock {
+    pub id: u32,
+    pub pool_address: Addr,
+    /// None when the pool was never synced through the manager
+    pub last_sync: Option<SyncRecord>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DueForSyncResponse {
+    pub stocks: Vec<DueStock>,
+    /// STKN SyncDue would pay if all of these pools sync
+    pub reward: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockCountResponse {
+    pub count: u32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BootstrapStatusResponse {
+    pub stage: BootstrapStage,
+    pub last_error: Option<String>,
+    pub pusd_address: Option<Addr>,
+    pub staking_address: Option<Addr>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SubcontractRoleResponse {
+    pub address: Addr,
+    pub is_owner: bool,
+    /// None for the owner and the protocol contracts
+    pub stock_id: Option<u32>,
+    pub role: Option<ContractRole>,
+    pub reason: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingOwnerResponse {
+    pub pending_owner: Option<Addr>,
+    pub expiry: Option<Expiration>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoleMembersResponse {
+    pub role: Role,
+    pub members: Vec<Addr>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockStatusResponse {
+    pub id: u32,
+    pub status: StockStatus,
+    /// false when either the manager or this stock does not accept new mints
+    pub mint_enabled: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReserveHistoryResponse {
+    pub records: Vec<(u64, ReserveRecord)>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockMintedResponse {
+    pub id: u32,
+    pub pusd_minted: Uint128,
+    pub pusd_outstanding: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SolvencyResponse {
+    pub pusd_supply: Uint128,
+    /// STKN held by the manager, without accrued fees
+    pub stkn_reserve: Uint128,
+    pub price: Uint128,
+    /// stkn_reserve valued in PUSD at price
+    pub reserve_value: Uint128,
+    /// None while no PUSD exists
+    pub collateral_ratio: Option<Decimal>,
+    pub min_collateral_ratio: Option<Decimal>,
+    /// Paginated with start_after and limit
+    pub stocks: Vec<StockMintedResponse>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SettlementResponse {
+    pub settlement: Option<Settlement>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub price: Uint128,
+    /// "oracle" or "manual"
+    pub source: String,
+    pub updated_at: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateSwapResponse {
+    pub price: Uint128,
+    pub offer_amount: Uint128,
+    pub return_amount: Uint128,
+    /// Fee in STKN
+    pub fee_amount: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SubcontractCodeInfo {
+    pub target: Subcontract,
+    pub address: Addr,
+    pub code_id: u64,
+    /// Code id the manager would migrate this subcontract to
+    pub latest_code_id: u64,
+    pub admin: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockCodeIdsResponse {
+    pub id: u32,
+    pub subcontracts: Vec<SubcontractCodeInfo>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MintUsageResponse {
+    pub id: u32,
+    pub kind: MintKind,
+    pub limit: Option<MintLimit>,
+    pub total_minted: Uint128,
+    pub outstanding: Uint128,
+    pub window_minted: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolExecuteMsg {
+    SyncPrice {}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubcontractExecuteMsg {
+    /// Sent to the pool and the trading contract, which then refuse swaps, new liquidity and
+    /// new positions. The trading handler lives in the stocktrading contract.
+    SetWithdrawOnly {},
+    /// Sent to the stock token and the pool. The token multiplies balances and supply by
+    /// numerator / denominator, the pool its stock reserve. The token handler lives in the
+    /// cw20-stock contract.
+    Split {
+        numerator: u64,
+        denominator: u64
+    }
+}
+
+/// Same limit as cw20-base
+const LOGO_SIZE_CAP: usize = 5 * 1024;
+
+/// The cw20-base logo checks, so a bad logo fails here and not in the forwarded message
+pub fn verify_logo(logo: &Logo) -> Result<(), ContractError> {
+    let invalid = |reason: &str| ContractError::InvalidLogo { reason: reason.to_string() };
+    match logo {
+        Logo::Embedded(EmbeddedLogo::Svg(logo)) => {
+            let preamble = logo.split_inclusive(|c| *c == b'>').next().unwrap_or_default();
+            if !(preamble.starts_with(b"<?xml ") && preamble.ends_with(b"?>")) {
+                return Err(invalid("Invalid xml preamble for SVG"));
+            }
+            if logo.len() > LOGO_SIZE_CAP {
+                return Err(invalid("Logo binary data exceeds 5KB limit"));
+            }
+        },
+        Logo::Embedded(EmbeddedLogo::Png(logo)) => {
+            const HEADER: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+            if logo.len() > LOGO_SIZE_CAP {
+                return Err(invalid("Logo binary data exceeds 5KB limit"));
+            }
+            if !logo.starts_with(&HEADER) {
+                return Err(invalid("Invalid png header"));
+            }
+        },
+        Logo::Url(_) => {}
+    }
+    Ok(())
+}
+
+pub fn get_oracle_price(
+    querier: QuerierWrapper,
+    oracle_address: Addr
+) -> StdResult<OraclePriceResponse> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle_address.into(),
+        msg: to_binary(&OracleQueryMsg::Price {})?,
+    }))
+}
+
+pub fn check_owner(
+    querier: QuerierWrapper,
+    manager_address: Addr,
+    address: Addr
+) -> Result<bool, ContractError> {
+    let manager_response = get_manager_config(querier, manager_address)?;
+    if manager_response.owner.as_ref() != Some(&address) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(true)
+}
+
+pub fn check_enabled(
+    querier: QuerierWrapper,
+    manager_address: Addr
+) -> Result<bool, ContractError> {
+    let manager_response = get_manager_config(querier, manager_address)?;
+    if !manager_response.enabled {
+        return Err(ContractError::Disabled {});
+    }
+    Ok(true)
+}
@@ -0,0 +1,283 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{to_binary, Addr, DepsMut, Env, Order, QueryRequest, StdResult, Uint128, WasmQuery};
+use cw_storage_plus::{Item, Map};
+use cw20::{Cw20QueryMsg, TokenInfoResponse};
+
+use crate::state::{
+    Config, CONFIG, CONFIG_KEY, STOCKS, STOCKS_KEY, STOCK_COUNT, STOCK_SYMBOLS, ACCRUED_FEES,
+    RESERVE_HISTORY_COUNT, Bootstrap, BOOTSTRAP, PusdConfig, StakingConfig, next_bootstrap_stage, symbol_key,
+    register_protocol_addresses, register_stock_addresses, SCHEDULED_OPERATION_COUNT, PROPOSAL_COUNT,
+    CORPORATE_ACTION_COUNT
+};
+use crate::manager::{StockInfo, StockMetadata, StockStatus};
+
+/// Config as stored by stknmanager 1.0.x
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigV1_0 {
+    pub owner: Addr,
+    pub stkn_address: Addr,
+    pub pusd_address: Addr,
+    pub staking_address: Addr,
+
+    pub cw20_code_id: u64,
+    pub stock_code_id: u64,
+
+    pub staking_code_id: u64,
+    pub pool_code_id: u64,
+    pub shorting_code_id: u64,
+    pub trading_code_id: u64,
+    pub providing_code_id: u64,
+
+    pub price: Uint128,
+
+    pub max_stock_id: u32,
+    pub enabled: bool,
+
+    pub providing_sync_interval: u64
+}
+
+/// StockInfo as stored by stknmanager 1.0.x
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockInfoV1_0 {
+    pub id: u32,
+    pub stock_address: Addr,
+    pub pool_address: Addr,
+    pub shorting_address: Addr,
+    pub trading_address: Addr,
+    pub providing_address: Addr
+}
+
+const CONFIG_V1_0: Item<ConfigV1_0> = Item::new(CONFIG_KEY);
+const STOCKS_V1_0: Map<u32, StockInfoV1_0> = Map::new(STOCKS_KEY);
+
+/// Fills the Config and StockInfo fields added after 1.0 and builds the new indexes.
+/// The PUSD url is left empty, migrate sets it from MigrateMsg.
+pub fn migrate_from_v1_0(deps: DepsMut, env: &Env) -> StdResult<()> {
+    let old = CONFIG_V1_0.load(deps.storage)?;
+
+    let cfg = Config {
+        owner: Some(old.owner.clone()),
+        stkn_address: old.stkn_address,
+        pusd_address: old.pusd_address,
+        staking_address: old.staking_address,
+
+        cw20_code_id: old.cw20_code_id,
+        stock_code_id: old.stock_code_id,
+
+        staking_code_id: old.staking_code_id,
+        pool_code_id: old.pool_code_id,
+        shorting_code_id: old.shorting_code_id,
+        trading_code_id: old.trading_code_id,
+        providing_code_id: old.providing_code_id,
+
+        price: old.price,
+        price_updated_at: env.block.time.seconds(),
+        oracle_address: None,
+        max_price_age: None,
+
+        buy_pusd_fee_bps: 0,
+        sell_pusd_fee_bps: 0,
+        fee_collector: old.owner,
+
+        min_stkn_reserve: Uint128::zero(),
+        min_collateral_ratio: None,
+
+        max_stock_id: old.max_stock_id,
+        enabled: old.enabled,
+
+        providing_sync_interval: old.providing_sync_interval,
+        keeper_reward: Uint128::zero()
+    };
+    CONFIG.save(deps.storage, &cfg)?;
+
+    register_protocol_addresses(deps.storage, &cfg)?;
+
+    // Values that 1.0 hard-coded in instantiate and reply
+    BOOTSTRAP.save(deps.storage, &Bootstrap {
+        stage: next_bootstrap_stage(&cfg),
+        last_error: None,
+        pusd: PusdConfig {
+            name: String::from("PUSD"),
+            symbol: String::from("PUSD"),
+            cap: None,
+            url: String::new(),
+            label: String::from("PUSD : stable coin of Stocken")
+        },
+        staking: StakingConfig {
+            lock_days: vec![30, 60, 120],
+            ratios: vec![20000, 40000, 80000],
+            label: String::from("STKN Staking")
+        }
+    })?;
+
+    let stocks = STOCKS_V1_0
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (id, old) in stocks.iter() {
+        // 1.0 did not keep the symbol, read it back from the stock token
+        let token_info: TokenInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: old.stock_address.clone().into(),
+            msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+        }))?;
+
+        // 1.0 allowed duplicated symbols, the lowest id keeps the index entry
+        let key = symbol_key(&token_info.symbol);
+        if !STOCK_SYMBOLS.has(deps.storage, &key) {
+            STOCK_SYMBOLS.save(deps.storage, &key, id)?;
+        }
+
+        let stock_info = StockInfo {
+            id: old.id,
+            symbol: token_info.symbol,
+            stock_address: old.stock_address.clone(),
+            pool_address: old.pool_address.clone(),
+            shorting_address: old.shorting_address.clone(),
+            trading_address: old.trading_address.clone(),
+            providing_address: old.providing_address.clone(),
+            status: StockStatus::Active,
+            metadata: StockMetadata::default()
+        };
+        STOCKS.save(deps.storage, *id, &stock_info)?;
+        register_stock_addresses(deps.storage, &stock_info)?;
+    }
+
+    STOCK_COUNT.save(deps.storage, &(stocks.len() as u32))?;
+    ACCRUED_FEES.save(deps.storage, &Uint128::zero())?;
+    RESERVE_HISTORY_COUNT.save(deps.storage, &0u64)?;
+    SCHEDULED_OPERATION_COUNT.save(deps.storage, &0u64)?;
+    PROPOSAL_COUNT.save(deps.storage, &0u64)?;
+    CORPORATE_ACTION_COUNT.save(deps.storage, &0u64)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{ContractResult, SystemError, SystemResult};
+    use cw2::{get_contract_version, set_contract_version};
+
+    use super::*;
+    use crate::contract::{migrate, query_check_stock_subcontract, query_stock_by_symbol};
+    use crate::msg::MigrateMsg;
+    use crate::state::BootstrapStage;
+    use crate::ContractError;
+
+    fn legacy_config() -> ConfigV1_0 {
+        ConfigV1_0 {
+            owner: Addr::unchecked("owner"),
+            stkn_address: Addr::unchecked("stkn"),
+            pusd_address: Addr::unchecked("pusd"),
+            staking_address: Addr::unchecked("staking"),
+            cw20_code_id: 1,
+            stock_code_id: 2,
+            staking_code_id: 3,
+            pool_code_id: 4,
+            shorting_code_id: 5,
+            trading_code_id: 6,
+            providing_code_id: 7,
+            price: Uint128::new(2_000_000),
+            max_stock_id: 2,
+            enabled: true,
+            providing_sync_interval: 600
+        }
+    }
+
+    fn legacy_stock(id: u32, stock: &str) -> StockInfoV1_0 {
+        StockInfoV1_0 {
+            id,
+            stock_address: Addr::unchecked(stock),
+            pool_address: Addr::unchecked(format!("{}_pool", stock)),
+            shorting_address: Addr::unchecked(format!("{}_shorting", stock)),
+            trading_address: Addr::unchecked(format!("{}_trading", stock)),
+            providing_address: Addr::unchecked(format!("{}_providing", stock))
+        }
+    }
+
+    fn mock_token_info(query: &WasmQuery) -> SystemResult<ContractResult<cosmwasm_std::Binary>> {
+        match query {
+            WasmQuery::Smart { contract_addr, .. } => {
+                let symbol = match contract_addr.as_str() {
+                    "aapl" | "aapl_dup" => "AAPL",
+                    _ => "TSLA",
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&TokenInfoResponse {
+                    name: symbol.to_string(),
+                    symbol: symbol.to_string(),
+                    decimals: 6,
+                    total_supply: Uint128::zero()
+                }).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest { kind: "wasm".to_string() }),
+        }
+    }
+
+    #[test]
+    fn migrate_from_v1_0_fills_new_fields() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(mock_token_info);
+        set_contract_version(&mut deps.storage, "stknmanager", "1.0.0").unwrap();
+        CONFIG_V1_0.save(&mut deps.storage, &legacy_config()).unwrap();
+        STOCKS_V1_0.save(&mut deps.storage, 1, &legacy_stock(1, "aapl")).unwrap();
+        STOCKS_V1_0.save(&mut deps.storage, 2, &legacy_stock(2, "tsla")).unwrap();
+
+        let pusd_url = Some(String::from("https://stocken.io/pusd.png"));
+        migrate(deps.as_mut(), mock_env(), MigrateMsg { pusd_url: pusd_url.clone() }).unwrap();
+
+        let cfg = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(cfg.owner, Some(Addr::unchecked("owner")));
+        assert_eq!(cfg.price, Uint128::new(2_000_000));
+        assert_eq!(cfg.price_updated_at, mock_env().block.time.seconds());
+        assert_eq!(cfg.fee_collector, Addr::unchecked("owner"));
+        assert_eq!(cfg.buy_pusd_fee_bps, 0);
+        assert_eq!(cfg.oracle_address, None);
+        assert_eq!(cfg.max_stock_id, 2);
+        assert_eq!(cfg.keeper_reward, Uint128::zero());
+
+        let stock = STOCKS.load(&deps.storage, 2).unwrap();
+        assert_eq!(stock.symbol, "TSLA");
+        assert_eq!(stock.status, StockStatus::Active);
+        assert_eq!(stock.metadata, StockMetadata::default());
+        assert_eq!(stock.pool_address, Addr::unchecked("tsla_pool"));
+        assert_eq!(query_stock_by_symbol(deps.as_ref(), "aapl".to_string()).unwrap().id, 1);
+        assert!(query_check_stock_subcontract(deps.as_ref(), 2, Addr::unchecked("tsla_pool")).unwrap());
+        assert!(!query_check_stock_subcontract(deps.as_ref(), 1, Addr::unchecked("tsla_pool")).unwrap());
+        assert!(query_check_stock_subcontract(deps.as_ref(), 1, Addr::unchecked("staking")).unwrap());
+
+        assert_eq!(STOCK_COUNT.load(&deps.storage).unwrap(), 2);
+        let bootstrap = BOOTSTRAP.load(&deps.storage).unwrap();
+        assert_eq!(bootstrap.stage, BootstrapStage::Done);
+        assert_eq!(Some(bootstrap.pusd.url), pusd_url);
+        assert_eq!(ACCRUED_FEES.load(&deps.storage).unwrap(), Uint128::zero());
+        assert_eq!(get_contract_version(&deps.storage).unwrap().version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn migrate_from_v1_0_keeps_first_duplicated_symbol() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(mock_token_info);
+        set_contract_version(&mut deps.storage, "stknmanager", "1.0.0").unwrap();
+        CONFIG_V1_0.save(&mut deps.storage, &legacy_config()).unwrap();
+        STOCKS_V1_0.save(&mut deps.storage, 1, &legacy_stock(1, "aapl")).unwrap();
+        STOCKS_V1_0.save(&mut deps.storage, 2, &legacy_stock(2, "aapl_dup")).unwrap();
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg { pusd_url: None }).unwrap();
+
+        assert_eq!(STOCK_SYMBOLS.load(&deps.storage, "AAPL").unwrap(), 1);
+        assert_eq!(STOCKS.load(&deps.storage, 2).unwrap().symbol, "AAPL");
+    }
+
+    #[test]
+    fn migrate_rejects_downgrade_and_other_contracts() {
+        let mut deps = mock_dependencies();
+        set_contract_version(&mut deps.storage, "stknmanager", "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { pusd_url: None }).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+
+        set_contract_version(&mut deps.storage, "crates.io:cw20-base", "0.14.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { pusd_url: None }).unwrap_err();
+        assert_eq!(err, ContractError::CannotMigrate { previous_contract: "crates.io:cw20-base".to_string() });
+    }
+}
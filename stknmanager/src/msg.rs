@@ -1,7 +1,9 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cw20::{Cw20ReceiveMsg};
-use cosmwasm_std::{Uint128, Addr};
+use cw20::{Cw20ReceiveMsg, Expiration, Logo};
+use cosmwasm_std::{Uint128, Addr, Binary, Decimal};
+use crate::state::{CorporateAction, CircuitBreaker, TimelockCategory, Role, MintKind, MintLimit, Subcontract, PusdConfig, StakingConfig};
+use crate::manager::{StockMetadata, StockStatus};
 
 // use marble_collection::msg::{InstantiateMsg as CollectionInstantiateMsg, ExecuteMsg as CollectionExecuteMsg};
 
@@ -19,16 +21,103 @@ pub struct InstantiateMsg {
     pub providing_code_id: u64,
     
     pub price: Uint128,
-    pub pusd_url: String,
+    pub oracle_address: Option<Addr>,
+    pub max_price_age: Option<u64>,
+    pub buy_pusd_fee_bps: u64,
+    pub sell_pusd_fee_bps: u64,
+    /// Defaults to the instantiator
+    pub fee_collector: Option<Addr>,
+
+    pub pusd: PusdConfig,
+    pub staking: StakingConfig,
+    /// Existing PUSD token to adopt instead of instantiating one, the manager has to be its minter
+    pub pusd_address: Option<Addr>,
+    /// Existing staking contract to adopt instead of instantiating one
+    pub staking_address: Option<Addr>,
 
     pub providing_sync_interval: u64
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketingToken {
+    Pusd,
+    Stock {
+        id: u32
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    UpdateOwner {
-        owner: Addr
+    /// Retries the instantiation of the current bootstrap stage
+    ResumeBootstrap {},
+    /// Queues an admin message, it can be executed from eta on
+    Schedule {
+        msg: Box<ExecuteMsg>,
+        eta: u64
+    },
+    /// Runs a scheduled operation whose eta has passed, callable by anyone
+    Execute {
+        id: u64
+    },
+    /// Drops a scheduled operation, by its proposer or the owner
+    Cancel {
+        id: u64
+    },
+    /// Owner only, once. Make the manager its own owner through a proposed AcceptOwnership
+    /// (after ProposeOwner to the manager address) to put the multisig in charge.
+    SetupMultisig {
+        members: Vec<Addr>,
+        threshold: u32,
+        voting_period: u64
+    },
+    /// Only through a passed proposal
+    UpdateMultisig {
+        add: Vec<Addr>,
+        remove: Vec<Addr>,
+        threshold: Option<u32>,
+        voting_period: Option<u64>
+    },
+    /// Members propose an ExecuteMsg for the manager (target None) or one of its registered contracts
+    Propose {
+        title: String,
+        target: Option<Addr>,
+        msg: Binary
+    },
+    Vote {
+        id: u64,
+        yes: bool
+    },
+    /// Sends the message of a passed proposal, callable by anyone
+    ExecuteProposal {
+        id: u64
+    },
+    /// A delay of 0 lets the category run without scheduling
+    UpdateTimelockDelay {
+        category: TimelockCategory,
+        delay: u64
+    },
+    /// Forwarded to the staking contract
+    UpdateStakingLockTiers {
+        lock_days: Vec<u64>,
+        ratios: Vec<u64>
+    },
+    ProposeOwner {
+        owner: Addr,
+        expiry: Option<Expiration>
+    },
+    AcceptOwnership {},
+    CancelOwnershipProposal {},
+    /// Clears the owner for good, the multisig cannot act as owner afterwards either
+    RenounceOwnership {},
+    GrantRole {
+        role: Role,
+        address: Addr
+    },
+    RevokeRole {
+        role: Role,
+        address: Addr
     },
     UpdateEnabled {
         enabled: bool
@@ -36,17 +125,130 @@ pub enum ExecuteMsg {
     UpdatePrice {
         price: Uint128
     },
-    RemoveStock {
+    UpdateOracle {
+        oracle_address: Option<Addr>,
+        max_price_age: Option<u64>
+    },
+    UpdateFees {
+        buy_pusd_fee_bps: u64,
+        sell_pusd_fee_bps: u64,
+        fee_collector: Addr
+    },
+    WithdrawStkn {
+        amount: Uint128,
+        recipient: Addr
+    },
+    UpdateMinStknReserve {
+        amount: Uint128
+    },
+    UpdateMinCollateralRatio {
+        ratio: Option<Decimal>
+    },
+    UpdateKeeperReward {
+        amount: Uint128
+    },
+    /// Sends SyncPrice to the pools due for it and pays the caller the keeper reward for the pools
+    /// that synced, a failing pool is skipped
+    SyncDue {},
+    /// Sent by a pool after SyncPrice
+    RecordSync {},
+    /// Sent by the manager to itself at the end of SyncDue
+    PayKeeperReward {},
+    /// Sends accrued STKN fees to the fee collector, everything when amount is None
+    WithdrawFees {
+        amount: Option<Uint128>
+    },
+    /// Freezes the stock price and supply, puts the pool and the trading contract in withdraw-only mode and opens the redemption window.
+    /// Holders redeem by sending stock tokens to the manager with ReceiveMsg::RedeemDelisted
+    DelistStock {
+        id: u32,
+        /// Length of the redemption window in seconds
+        redemption_period: u64
+    },
+    /// Moves a delisted stock to the archive once its redemption window has closed
+    ArchiveStock {
+        id: u32
+    },
+    UpdateMintLimit {
+        id: u32,
+        kind: MintKind,
+        limit: MintLimit
+    },
+    /// None removes the circuit breaker of the stock
+    UpdateCircuitBreaker {
+        id: u32,
+        breaker: Option<CircuitBreaker>
+    },
+    /// Rescales the stock token, its pool reserve and the stock mint limits and accounting.
+    /// Shorting and trading positions are not rescaled, the stock has to be paused and
+    /// is refused while those contracts report open positions
+    CorporateAction(CorporateAction),
+    /// Re-enables a stock paused by its circuit breaker, owner or guardian
+    Resume {
+        id: u32
+    },
+    /// Samples the stock price into its circuit breaker window, callable by anyone
+    CheckCircuitBreaker {
         id: u32
     },
-    RemoveAllStocks {
+    /// Registry symbol only, the stock token keeps the symbol it was instantiated with
+    UpdateStockSymbol {
+        id: u32,
+        symbol: String
+    },
+    UpdateStockStatus {
+        id: u32,
+        status: StockStatus
+    },
+    /// Only the given code ids are changed
+    UpdateCodeIds {
+        cw20_code_id: Option<u64>,
+        stock_code_id: Option<u64>,
+        pool_code_id: Option<u64>,
+        staking_code_id: Option<u64>,
+        shorting_code_id: Option<u64>,
+        trading_code_id: Option<u64>,
+        providing_code_id: Option<u64>
+    },
+    /// Migrates the target subcontract of each stock to the code id stored in Config
+    MigrateStocks {
+        ids: Vec<u32>,
+        target: Subcontract,
+        migrate_msg: Binary
     },
     AddStock {
         name: String,
         symbol: String,
-        url: String
+        url: String,
+        metadata: Option<StockMetadata>
+    },
+    /// Forwarded to the token, the manager has to be its marketing address.
+    /// Stock tokens refuse a new marketing address
+    UpdateTokenMarketing {
+        token: MarketingToken,
+        project: Option<String>,
+        description: Option<String>,
+        marketing: Option<String>
+    },
+    /// Forwarded to the token after the same logo checks as cw20-base
+    UploadTokenLogo {
+        token: MarketingToken,
+        logo: Logo
+    },
+    /// Replaces the metadata, its description is pushed to the stock token marketing info
+    /// only when the manager is the marketing address, otherwise it is just stored
+    UpdateStockMetadata {
+        id: u32,
+        metadata: StockMetadata
     },
     Receive(Cw20ReceiveMsg),
+    /// Freezes all prices, stops minting and snapshots the first batch of stocks
+    GlobalSettlement {},
+    /// Snapshots the next batch of stocks, anyone can call it until the settlement is complete
+    /// and PUSD and stock token redemptions open
+    SettleStocks {
+        limit: Option<u32>
+    },
     MintPusd {
         id: u32,
         recipient: Addr,
@@ -69,9 +271,35 @@ pub enum ExecuteMsg {
 pub enum ReceiveMsg {
     FundStkn {},
     Swap {
-        expected_amount: Uint128
+        /// Minimum amount of the other token to receive
+        min_output: Option<Uint128>,
+        /// Maximum STKN price in PUSD accepted when buying STKN
+        max_price: Option<Uint128>,
+        deadline: Option<Expiration>,
+        /// Defaults to the sender
+        recipient: Option<String>
+    },
+    /// Only during global settlement
+    RedeemPusd {
+        recipient: Option<String>
+    },
+    /// Stock tokens sent by a holder during global settlement, paid in PUSD at the frozen price
+    SettleStock {
+        recipient: Option<String>
+    },
+    /// Stock tokens of a delisted stock sent by a holder, paid in PUSD at the delisting price
+    RedeemDelisted {
+        recipient: Option<String>
+    },
+    /// Burns PUSD or stock tokens of the stock and lowers its outstanding mint amount,
+    /// only from the stock subcontracts
+    Burn {
+        id: u32
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// Logo url of the PUSD token a migrated manager still has to instantiate
+    pub pusd_url: Option<String>
+}
@@ -0,0 +1,181 @@
+use cosmwasm_std::{Addr, Empty, Uint128};
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+
+use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::state::{PusdConfig, Role, StakingConfig};
+use crate::manager::{ManagerQueryMsg, PriceResponse};
+
+const OWNER: &str = "owner";
+const MAX_PRICE_AGE: u64 = 3600;
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+fn manager_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        crate::contract::execute,
+        crate::contract::instantiate,
+        crate::contract::query,
+    ).with_reply(crate::contract::reply))
+}
+
+fn oracle_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        mockoracle::contract::execute,
+        mockoracle::contract::instantiate,
+        mockoracle::contract::query,
+    ))
+}
+
+/// Manager with a fresh oracle at 3 PUSD and a manual price of 2 PUSD, PUSD is instantiated
+/// by the bootstrap and the staking contract is adopted
+fn setup() -> (App, Addr, Addr) {
+    let mut app = App::default();
+    let cw20_code_id = app.store_code(cw20_contract());
+    let manager_code_id = app.store_code(manager_contract());
+    let oracle_code_id = app.store_code(oracle_contract());
+
+    let oracle = app.instantiate_contract(
+        oracle_code_id,
+        Addr::unchecked(OWNER),
+        &mockoracle::msg::InstantiateMsg { price: Uint128::new(3_000_000) },
+        &[],
+        "oracle",
+        None,
+    ).unwrap();
+
+    let manager = app.instantiate_contract(
+        manager_code_id,
+        Addr::unchecked(OWNER),
+        &InstantiateMsg {
+            stkn_address: Addr::unchecked("stkn"),
+            cw20_code_id,
+            stock_code_id: 0,
+            pool_code_id: 0,
+            staking_code_id: 0,
+            shorting_code_id: 0,
+            trading_code_id: 0,
+            providing_code_id: 0,
+            price: Uint128::new(2_000_000),
+            oracle_address: Some(oracle.clone()),
+            max_price_age: Some(MAX_PRICE_AGE),
+            buy_pusd_fee_bps: 0,
+            sell_pusd_fee_bps: 0,
+            fee_collector: None,
+            pusd: PusdConfig {
+                name: String::from("PUSD"),
+                symbol: String::from("PUSD"),
+                cap: None,
+                url: String::from("https://stocken.io/pusd.png"),
+                label: String::from("PUSD")
+            },
+            staking: StakingConfig {
+                lock_days: vec![30],
+                ratios: vec![20000],
+                label: String::from("STKN Staking")
+            },
+            pusd_address: None,
+            staking_address: Some(Addr::unchecked("staking")),
+            providing_sync_interval: 600
+        },
+        &[],
+        "stknmanager",
+        None,
+    ).unwrap();
+
+    (app, manager, oracle)
+}
+
+fn query_price(app: &App, manager: &Addr) -> Result<PriceResponse, cosmwasm_std::StdError> {
+    app.wrap().query_wasm_smart(manager.clone(), &ManagerQueryMsg::Price {})
+}
+
+fn advance(app: &mut App, seconds: u64) {
+    app.update_block(|block| block.time = block.time.plus_seconds(seconds));
+}
+
+#[test]
+fn fresh_oracle_price_is_used() {
+    let (app, manager, _oracle) = setup();
+
+    let price = query_price(&app, &manager).unwrap();
+    assert_eq!(price.source, "oracle");
+    assert_eq!(price.price, Uint128::new(3_000_000));
+}
+
+#[test]
+fn stale_oracle_falls_back_to_fresh_manual_price() {
+    let (mut app, manager, _oracle) = setup();
+    advance(&mut app, MAX_PRICE_AGE + 1);
+
+    // the manual price was set at instantiation and is stale as well
+    assert!(query_price(&app, &manager).is_err());
+
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        manager.clone(),
+        &ExecuteMsg::UpdatePrice { price: Uint128::new(2_500_000) },
+        &[],
+    ).unwrap();
+
+    let price = query_price(&app, &manager).unwrap();
+    assert_eq!(price.source, "manual");
+    assert_eq!(price.price, Uint128::new(2_500_000));
+
+    advance(&mut app, MAX_PRICE_AGE + 1);
+    assert!(query_price(&app, &manager).is_err());
+}
+
+#[test]
+fn refreshed_oracle_takes_over_again() {
+    let (mut app, manager, oracle) = setup();
+    advance(&mut app, MAX_PRICE_AGE + 1);
+
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        oracle,
+        &mockoracle::msg::ExecuteMsg::SetPrice { price: Uint128::new(4_000_000), last_updated: None },
+        &[],
+    ).unwrap();
+
+    let price = query_price(&app, &manager).unwrap();
+    assert_eq!(price.source, "oracle");
+    assert_eq!(price.price, Uint128::new(4_000_000));
+}
+
+#[test]
+fn price_setter_updates_oracle() {
+    let (mut app, manager, _oracle) = setup();
+
+    let err = app.execute_contract(
+        Addr::unchecked("setter"),
+        manager.clone(),
+        &ExecuteMsg::UpdateOracle { oracle_address: None, max_price_age: None },
+        &[],
+    );
+    assert!(err.is_err());
+
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        manager.clone(),
+        &ExecuteMsg::GrantRole { role: Role::PriceSetter, address: Addr::unchecked("setter") },
+        &[],
+    ).unwrap();
+    app.execute_contract(
+        Addr::unchecked("setter"),
+        manager.clone(),
+        &ExecuteMsg::UpdateOracle { oracle_address: None, max_price_age: None },
+        &[],
+    ).unwrap();
+
+    // without oracle and age limit the manual price is always used
+    advance(&mut app, MAX_PRICE_AGE + 1);
+    let price = query_price(&app, &manager).unwrap();
+    assert_eq!(price.source, "manual");
+    assert_eq!(price.price, Uint128::new(2_000_000));
+}
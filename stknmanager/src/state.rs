@@ -1,14 +1,16 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Binary, Decimal, StdResult, Storage, Uint128};
 use cw_storage_plus::{Item, Map};
-use crate::util::StockInfo;
+use cw20::Expiration;
+use crate::manager::{StockInfo, StockMetadata, StockStatus};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    /// Owner If None set, contract is frozen.
-    pub owner: Addr,
+    /// Changed through ProposeOwner and AcceptOwnership. None after RenounceOwnership, the owner
+    /// checks then reject every sender.
+    pub owner: Option<Addr>,
     pub stkn_address: Addr,
     pub pusd_address: Addr,
     pub staking_address: Addr,
@@ -22,16 +24,700 @@ pub struct Config {
     pub trading_code_id: u64,
     pub providing_code_id: u64,
 
+    /// Manual STKN price in PUSD, used when no oracle is set or the oracle price is stale
     pub price: Uint128,
+    pub price_updated_at: u64,
+    pub oracle_address: Option<Addr>,
+    /// Maximum age in seconds of the price used for swaps, None disables the check
+    pub max_price_age: Option<u64>,
+
+    /// Fee in basis points charged on STKN -> PUSD swaps
+    pub buy_pusd_fee_bps: u64,
+    /// Fee in basis points charged on PUSD -> STKN swaps
+    pub sell_pusd_fee_bps: u64,
+    pub fee_collector: Addr,
+
+    /// STKN that WithdrawStkn always leaves in the manager for PUSD redemptions
+    pub min_stkn_reserve: Uint128,
+    /// MintPusd is refused when it would bring STKN reserve value / PUSD supply below this
+    pub min_collateral_ratio: Option<Decimal>,
 
     pub max_stock_id: u32,
     pub enabled: bool,
 
-    pub providing_sync_interval: u64
+    /// Seconds after which a pool is due for SyncPrice
+    pub providing_sync_interval: u64,
+    /// STKN paid per pool synced through SyncDue
+    pub keeper_reward: Uint128
 }
 
 pub const CONFIG_KEY: &str = "config";
 pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
 
+/// Swap fees kept in STKN by the manager and not yet withdrawn
+pub const ACCRUED_FEES_KEY: &str = "accrued_fees";
+pub const ACCRUED_FEES: Item<Uint128> = Item::new(ACCRUED_FEES_KEY);
+
+/// Total STKN deposited with FundStkn by each funder
+pub const STKN_FUNDERS_KEY: &str = "stkn_funders";
+pub const STKN_FUNDERS: Map<&Addr, Uint128> = Map::new(STKN_FUNDERS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReserveRecord {
+    /// Inflows "fund", "swap_in", outflows "withdraw", "withdraw_fees", "keeper_reward", "swap_out", "transfer", "redeem"
+    pub action: String,
+    pub address: Addr,
+    pub amount: Uint128,
+    pub balance: Uint128,
+    pub time: u64
+}
+
+pub const RESERVE_HISTORY_KEY: &str = "reserve_history";
+pub const RESERVE_HISTORY: Map<u64, ReserveRecord> = Map::new(RESERVE_HISTORY_KEY);
+
+pub const RESERVE_HISTORY_COUNT_KEY: &str = "reserve_history_count";
+pub const RESERVE_HISTORY_COUNT: Item<u64> = Item::new(RESERVE_HISTORY_COUNT_KEY);
+
 pub const STOCKS_KEY: &str = "stocks";
 pub const STOCKS: Map<u32, StockInfo> = Map::new(STOCKS_KEY);
+
+/// Upper-cased ticker symbol -> stock id
+pub const STOCK_SYMBOLS_KEY: &str = "stock_symbols";
+pub const STOCK_SYMBOLS: Map<&str, u32> = Map::new(STOCK_SYMBOLS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CorporateAction {
+    /// Every stock token becomes numerator / denominator tokens, a reverse split has numerator < denominator
+    Split {
+        id: u32,
+        numerator: u64,
+        denominator: u64
+    }
+}
+
+impl CorporateAction {
+    pub fn stock_id(&self) -> u32 {
+        match self {
+            CorporateAction::Split { id, .. } => *id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CorporateActionRecord {
+    pub id: u64,
+    pub action: CorporateAction,
+    pub height: u64,
+    pub time: u64
+}
+
+pub const CORPORATE_ACTIONS_KEY: &str = "corporate_actions";
+pub const CORPORATE_ACTIONS: Map<u64, CorporateActionRecord> = Map::new(CORPORATE_ACTIONS_KEY);
+
+pub const CORPORATE_ACTION_COUNT_KEY: &str = "corporate_action_count";
+pub const CORPORATE_ACTION_COUNT: Item<u64> = Item::new(CORPORATE_ACTION_COUNT_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SyncRecord {
+    pub height: u64,
+    pub time: u64
+}
+
+/// Last SyncPrice completed by the pool of each stock, reported by the pool with RecordSync
+pub const LAST_SYNCS_KEY: &str = "last_syncs";
+pub const LAST_SYNCS: Map<u32, SyncRecord> = Map::new(LAST_SYNCS_KEY);
+
+/// Last stock scanned by SyncDue, the next scan starts after it and wraps around
+pub const SYNC_CURSOR_KEY: &str = "sync_cursor";
+pub const SYNC_CURSOR: Item<u32> = Item::new(SYNC_CURSOR_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeeperRun {
+    pub keeper: Addr,
+    /// Pools that reported their sync so far
+    pub synced: u32
+}
+
+/// Only set during a SyncDue transaction, removed by PayKeeperReward at its end
+pub const KEEPER_RUN_KEY: &str = "keeper_run";
+pub const KEEPER_RUN: Item<KeeperRun> = Item::new(KEEPER_RUN_KEY);
+
+pub const STOCK_COUNT_KEY: &str = "stock_count";
+pub const STOCK_COUNT: Item<u32> = Item::new(STOCK_COUNT_KEY);
+
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingOwner {
+    /// Address that has to call AcceptOwnership to become the new owner
+    pub owner: Addr,
+    /// Proposal can no longer be accepted once this is reached
+    pub expiry: Option<Expiration>
+}
+
+pub const PENDING_OWNER_KEY: &str = "pending_owner";
+pub const PENDING_OWNER: Item<PendingOwner> = Item::new(PENDING_OWNER_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// UpdatePrice, UpdateOracle
+    PriceSetter,
+    /// AddStock, DelistStock, ArchiveStock
+    Lister,
+    /// UpdateEnabled
+    Pauser,
+    /// WithdrawStkn, WithdrawFees, UpdateMinStknReserve
+    Treasurer,
+    /// Resume after a circuit breaker trip
+    Guardian
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::PriceSetter => "price_setter",
+            Role::Lister => "lister",
+            Role::Pauser => "pauser",
+            Role::Treasurer => "treasurer",
+            Role::Guardian => "guardian",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelockCategory {
+    /// UpdatePrice, UpdateOracle
+    Price,
+    /// UpdateFees, UpdateMinStknReserve, UpdateMinCollateralRatio, UpdateMintLimit, UpdateKeeperReward
+    Fees,
+    /// UpdateCodeIds, MigrateStocks, UpdateStakingLockTiers
+    CodeIds,
+    /// DelistStock, ArchiveStock
+    Listing,
+    /// WithdrawStkn
+    Treasury,
+    /// UpdateTimelockDelay
+    Timelock,
+    /// CorporateAction
+    CorporateAction,
+    /// UpdateCircuitBreaker
+    CircuitBreaker,
+    /// GrantRole, RevokeRole
+    Roles
+}
+
+impl TimelockCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelockCategory::Price => "price",
+            TimelockCategory::Fees => "fees",
+            TimelockCategory::CodeIds => "code_ids",
+            TimelockCategory::Listing => "listing",
+            TimelockCategory::Treasury => "treasury",
+            TimelockCategory::Timelock => "timelock",
+            TimelockCategory::CorporateAction => "corporate_action",
+            TimelockCategory::CircuitBreaker => "circuit_breaker",
+            TimelockCategory::Roles => "roles",
+        }
+    }
+}
+
+/// Who can send or schedule an admin message
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAuth {
+    Owner,
+    /// Holders of the role, and the owner
+    Role(Role)
+}
+
+/// Minimum delay in seconds per category, categories without delay run immediately
+pub const TIMELOCK_DELAYS_KEY: &str = "timelock_delays";
+pub const TIMELOCK_DELAYS: Map<&str, u64> = Map::new(TIMELOCK_DELAYS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScheduledOperation {
+    pub id: u64,
+    pub category: TimelockCategory,
+    /// Serialized ExecuteMsg
+    pub msg: Binary,
+    /// Runs with this address as sender
+    pub proposer: Addr,
+    pub scheduled_at: u64,
+    pub eta: u64
+}
+
+pub const SCHEDULED_OPERATIONS_KEY: &str = "scheduled_operations";
+pub const SCHEDULED_OPERATIONS: Map<u64, ScheduledOperation> = Map::new(SCHEDULED_OPERATIONS_KEY);
+
+pub const SCHEDULED_OPERATION_COUNT_KEY: &str = "scheduled_operation_count";
+pub const SCHEDULED_OPERATION_COUNT: Item<u64> = Item::new(SCHEDULED_OPERATION_COUNT_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MultisigConfig {
+    /// Yes votes needed to pass a proposal
+    pub threshold: u32,
+    /// Seconds a proposal stays open
+    pub voting_period: u64
+}
+
+pub const MULTISIG_CONFIG_KEY: &str = "multisig_config";
+pub const MULTISIG_CONFIG: Item<MultisigConfig> = Item::new(MULTISIG_CONFIG_KEY);
+
+pub const MULTISIG_MEMBERS_KEY: &str = "multisig_members";
+pub const MULTISIG_MEMBERS: Map<&Addr, bool> = Map::new(MULTISIG_MEMBERS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Open,
+    /// Threshold reached, waiting for ExecuteProposal
+    Passed,
+    /// Threshold can no longer be reached
+    Rejected,
+    /// Voting period ended before the threshold was reached
+    Expired,
+    Executed
+}
+
+impl ProposalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProposalStatus::Open => "open",
+            ProposalStatus::Passed => "passed",
+            ProposalStatus::Rejected => "rejected",
+            ProposalStatus::Expired => "expired",
+            ProposalStatus::Executed => "executed",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal {
+    pub id: u64,
+    pub title: String,
+    pub proposer: Addr,
+    /// The manager or a contract of one of its stocks
+    pub target: Addr,
+    /// ExecuteMsg sent to target by the manager
+    pub msg: Binary,
+    pub expires: u64,
+    pub yes_votes: u32,
+    pub no_votes: u32,
+    pub status: ProposalStatus
+}
+
+impl Proposal {
+    pub fn current_status(&self, now: u64) -> ProposalStatus {
+        if self.status == ProposalStatus::Open && now > self.expires {
+            ProposalStatus::Expired
+        } else {
+            self.status
+        }
+    }
+}
+
+pub const PROPOSALS_KEY: &str = "proposals";
+pub const PROPOSALS: Map<u64, Proposal> = Map::new(PROPOSALS_KEY);
+
+pub const PROPOSAL_COUNT_KEY: &str = "proposal_count";
+pub const PROPOSAL_COUNT: Item<u64> = Item::new(PROPOSAL_COUNT_KEY);
+
+/// (proposal id, member) -> voted yes
+pub const PROPOSAL_VOTES_KEY: &str = "proposal_votes";
+pub const PROPOSAL_VOTES: Map<(u64, &Addr), bool> = Map::new(PROPOSAL_VOTES_KEY);
+
+pub const ROLES_KEY: &str = "roles";
+pub const ROLES: Map<(&str, &Addr), bool> = Map::new(ROLES_KEY);
+
+pub fn symbol_key(symbol: &str) -> String {
+    symbol.to_uppercase()
+}
+
+/// Same rule as cw20-base: 3 to 12 letters or dashes
+pub fn is_valid_symbol(symbol: &str) -> bool {
+    let bytes = symbol.as_bytes();
+    if bytes.len() < 3 || bytes.len() > 12 {
+        return false;
+    }
+    bytes.iter().all(|byte| *byte == b'-' || byte.is_ascii_alphabetic())
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MintKind {
+    Pusd,
+    Stock
+}
+
+impl MintKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MintKind::Pusd => "pusd",
+            MintKind::Stock => "stock",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MintLimit {
+    /// Maximum outstanding amount of the stock, mints minus burns reported to the manager
+    pub ceiling: Option<Uint128>,
+    /// Length of the rolling rate limit window in seconds
+    pub window: u64,
+    /// Maximum amount minted within any window
+    pub window_limit: Option<Uint128>
+}
+
+/// Buckets a rolling window is split into
+pub const WINDOW_BUCKETS: u64 = 10;
+
+/// Amounts added over the last window seconds, kept in WINDOW_BUCKETS buckets. A bucket is dropped
+/// once all of it is older than the window, so the sum can cover up to one bucket more.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct RollingWindow {
+    /// (bucket start, amount), oldest first
+    pub buckets: Vec<(u64, Uint128)>
+}
+
+impl RollingWindow {
+    fn bucket_len(window: u64) -> u64 {
+        std::cmp::max(window / WINDOW_BUCKETS, 1)
+    }
+
+    /// Drops the buckets that ended before now - window
+    pub fn prune(&mut self, now: u64, window: u64) {
+        let bucket_len = Self::bucket_len(window);
+        self.buckets.retain(|(start, _)| now < start.saturating_add(bucket_len).saturating_add(window));
+    }
+
+    pub fn add(&mut self, now: u64, window: u64, amount: Uint128) -> StdResult<()> {
+        self.prune(now, window);
+        let bucket_len = Self::bucket_len(window);
+        let bucket_start = now - now % bucket_len;
+        match self.buckets.last_mut() {
+            Some((start, bucket)) if *start == bucket_start => {
+                *bucket = bucket.checked_add(amount)?;
+            },
+            _ => self.buckets.push((bucket_start, amount)),
+        }
+        Ok(())
+    }
+
+    pub fn total(&self) -> StdResult<Uint128> {
+        self.buckets
+            .iter()
+            .try_fold(Uint128::zero(), |total, (_, amount)| Ok(total.checked_add(*amount)?))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct MintUsage {
+    /// Everything ever minted
+    pub total_minted: Uint128,
+    /// Minted minus burned through ReceiveMsg::Burn
+    pub outstanding: Uint128,
+    pub window: RollingWindow
+}
+
+pub const MINT_LIMITS_KEY: &str = "mint_limits";
+pub const MINT_LIMITS: Map<(u32, &str), MintLimit> = Map::new(MINT_LIMITS_KEY);
+
+pub const MINT_USAGE_KEY: &str = "mint_usage";
+pub const MINT_USAGE: Map<(u32, &str), MintUsage> = Map::new(MINT_USAGE_KEY);
+
+/// Amount ever minted by each subcontract
+pub const CALLER_MINTED_KEY: &str = "caller_minted";
+pub const CALLER_MINTED: Map<(u32, &str, &Addr), Uint128> = Map::new(CALLER_MINTED_KEY);
+
+/// Pauses a stock when a threshold is passed within window seconds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CircuitBreaker {
+    /// Length of the rolling window in seconds
+    pub window: u64,
+    pub max_pusd_minted: Option<Uint128>,
+    pub max_stock_minted: Option<Uint128>,
+    /// Stock price move against any price seen within the window, in basis points
+    pub max_price_change_bps: Option<u64>
+}
+
+pub const CIRCUIT_BREAKERS_KEY: &str = "circuit_breakers";
+pub const CIRCUIT_BREAKERS: Map<u32, CircuitBreaker> = Map::new(CIRCUIT_BREAKERS_KEY);
+
+/// Lowest and highest price seen in each bucket of a rolling window, bucketed like RollingWindow
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PriceWindow {
+    /// (bucket start, lowest, highest), oldest first
+    pub buckets: Vec<(u64, Uint128, Uint128)>
+}
+
+impl PriceWindow {
+    pub fn prune(&mut self, now: u64, window: u64) {
+        let bucket_len = RollingWindow::bucket_len(window);
+        self.buckets.retain(|(start, _, _)| now < start.saturating_add(bucket_len).saturating_add(window));
+    }
+
+    pub fn add(&mut self, now: u64, window: u64, price: Uint128) {
+        self.prune(now, window);
+        let bucket_len = RollingWindow::bucket_len(window);
+        let bucket_start = now - now % bucket_len;
+        match self.buckets.last_mut() {
+            Some((start, low, high)) if *start == bucket_start => {
+                *low = std::cmp::min(*low, price);
+                *high = std::cmp::max(*high, price);
+            },
+            _ => self.buckets.push((bucket_start, price, price)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct BreakerWindow {
+    pub pusd_minted: RollingWindow,
+    pub stock_minted: RollingWindow,
+    /// Only sampled with a price threshold
+    pub prices: PriceWindow
+}
+
+pub const BREAKER_WINDOWS_KEY: &str = "breaker_windows";
+pub const BREAKER_WINDOWS: Map<u32, BreakerWindow> = Map::new(BREAKER_WINDOWS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BreakerTrip {
+    pub time: u64,
+    /// "pusd_volume", "stock_volume" or "price_change"
+    pub reason: String,
+    /// Status restored by Resume
+    pub previous_status: StockStatus
+}
+
+pub const BREAKER_TRIPS_KEY: &str = "breaker_trips";
+pub const BREAKER_TRIPS: Map<u32, BreakerTrip> = Map::new(BREAKER_TRIPS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Settlement {
+    pub time: u64,
+    /// Frozen STKN price in PUSD
+    pub stkn_price: Uint128,
+    pub pusd_supply: Uint128,
+    pub stkn_reserve: Uint128,
+    /// PUSD supply plus the stock tokens snapshotted so far valued at their frozen prices
+    pub total_claims: Uint128,
+    /// Last stock snapshotted, stocks are snapshotted in batches by SettleStocks
+    pub last_stock: Option<u32>,
+    /// Set once every stock is snapshotted, redemptions are open from then on
+    pub complete: bool,
+    /// STKN paid out per redeemed PUSD, zero until complete
+    pub redemption_rate: Decimal
+}
+
+pub const SETTLEMENT_KEY: &str = "settlement";
+pub const SETTLEMENT: Item<Settlement> = Item::new(SETTLEMENT_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SettledStock {
+    /// Frozen stock price in PUSD
+    pub price: Uint128,
+    /// Token supply at the snapshot, caps the settled amount
+    pub supply: Uint128,
+    pub settled: Uint128
+}
+
+pub const SETTLED_STOCKS_KEY: &str = "settled_stocks";
+pub const SETTLED_STOCKS: Map<u32, SettledStock> = Map::new(SETTLED_STOCKS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Delisting {
+    pub time: u64,
+    /// Frozen stock price in PUSD
+    pub price: Uint128,
+    /// Token supply at delisting, caps the redeemed amount
+    pub supply: Uint128,
+    pub redeemed: Uint128,
+    /// Stock tokens can be redeemed for PUSD until this time
+    pub redemption_end: u64
+}
+
+pub const DELISTINGS_KEY: &str = "delistings";
+pub const DELISTINGS: Map<u32, Delisting> = Map::new(DELISTINGS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ArchivedStock {
+    pub stock: StockInfo,
+    pub delisting: Delisting,
+    pub archived_at: u64
+}
+
+/// Delisted stocks whose redemption window has closed, by id
+pub const ARCHIVED_STOCKS_KEY: &str = "archived_stocks";
+pub const ARCHIVED_STOCKS: Map<u32, ArchivedStock> = Map::new(ARCHIVED_STOCKS_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Subcontract {
+    Stock,
+    Pool,
+    Shorting,
+    Trading,
+    Providing
+}
+
+impl Subcontract {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Subcontract::Stock => "stock",
+            Subcontract::Pool => "pool",
+            Subcontract::Shorting => "shorting",
+            Subcontract::Trading => "trading",
+            Subcontract::Providing => "providing",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractRole {
+    Stkn,
+    Pusd,
+    Staking,
+    Stock,
+    Pool,
+    Shorting,
+    Trading,
+    Providing
+}
+
+impl ContractRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContractRole::Stkn => "stkn",
+            ContractRole::Pusd => "pusd",
+            ContractRole::Staking => "staking",
+            ContractRole::Stock => "stock",
+            ContractRole::Pool => "pool",
+            ContractRole::Shorting => "shorting",
+            ContractRole::Trading => "trading",
+            ContractRole::Providing => "providing",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AddressRole {
+    /// None for the protocol contracts, which act for every stock
+    pub stock_id: Option<u32>,
+    pub role: ContractRole
+}
+
+pub const ADDRESS_ROLES_KEY: &str = "address_roles";
+pub const ADDRESS_ROLES: Map<&Addr, AddressRole> = Map::new(ADDRESS_ROLES_KEY);
+
+fn stock_addresses(stock_info: &StockInfo) -> [(&Addr, ContractRole); 5] {
+    [
+        (&stock_info.stock_address, ContractRole::Stock),
+        (&stock_info.pool_address, ContractRole::Pool),
+        (&stock_info.shorting_address, ContractRole::Shorting),
+        (&stock_info.trading_address, ContractRole::Trading),
+        (&stock_info.providing_address, ContractRole::Providing),
+    ]
+}
+
+/// Registers STKN, and PUSD and staking once they are set
+pub fn register_protocol_addresses(storage: &mut dyn Storage, cfg: &Config) -> StdResult<()> {
+    ADDRESS_ROLES.save(storage, &cfg.stkn_address, &AddressRole { stock_id: None, role: ContractRole::Stkn })?;
+    if cfg.pusd_address != cfg.stkn_address {
+        ADDRESS_ROLES.save(storage, &cfg.pusd_address, &AddressRole { stock_id: None, role: ContractRole::Pusd })?;
+    }
+    if cfg.staking_address != cfg.stkn_address {
+        ADDRESS_ROLES.save(storage, &cfg.staking_address, &AddressRole { stock_id: None, role: ContractRole::Staking })?;
+    }
+    Ok(())
+}
+
+pub fn register_stock_addresses(storage: &mut dyn Storage, stock_info: &StockInfo) -> StdResult<()> {
+    for (address, role) in stock_addresses(stock_info) {
+        ADDRESS_ROLES.save(storage, address, &AddressRole { stock_id: Some(stock_info.id), role })?;
+    }
+    Ok(())
+}
+
+pub fn unregister_stock_addresses(storage: &mut dyn Storage, stock_info: &StockInfo) {
+    for (address, _) in stock_addresses(stock_info) {
+        ADDRESS_ROLES.remove(storage, address);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapStage {
+    /// Waiting for the PUSD token
+    Pusd,
+    /// Waiting for the staking contract
+    Staking,
+    Done
+}
+
+impl BootstrapStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BootstrapStage::Pusd => "pusd",
+            BootstrapStage::Staking => "staking",
+            BootstrapStage::Done => "done",
+        }
+    }
+}
+
+/// PUSD has PUSD_DECIMALS decimals, the swap and collateral math relies on it
+pub const PUSD_DECIMALS: u8 = 6;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PusdConfig {
+    pub name: String,
+    pub symbol: String,
+    pub cap: Option<Uint128>,
+    pub url: String,
+    pub label: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingConfig {
+    pub lock_days: Vec<u64>,
+    pub ratios: Vec<u64>,
+    pub label: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Bootstrap {
+    pub stage: BootstrapStage,
+    /// Error of the last failed instantiation of the current stage
+    pub last_error: Option<String>,
+    pub pusd: PusdConfig,
+    pub staking: StakingConfig
+}
+
+/// Stage still to run, PUSD and staking addresses hold the STKN address until they are set
+pub fn next_bootstrap_stage(cfg: &Config) -> BootstrapStage {
+    if cfg.pusd_address == cfg.stkn_address {
+        BootstrapStage::Pusd
+    } else if cfg.staking_address == cfg.stkn_address {
+        BootstrapStage::Staking
+    } else {
+        BootstrapStage::Done
+    }
+}
+
+pub const BOOTSTRAP_KEY: &str = "bootstrap";
+pub const BOOTSTRAP: Item<Bootstrap> = Item::new(BOOTSTRAP_KEY);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingStock {
+    pub id: u32,
+    pub symbol: String,
+    pub metadata: StockMetadata
+}
+
+/// AddStock registrations waiting for their reply, keyed by reply id
+pub const PENDING_STOCKS_KEY: &str = "pending_stocks";
+pub const PENDING_STOCKS: Map<u64, PendingStock> = Map::new(PENDING_STOCKS_KEY);
@@ -0,0 +1 @@
+../../util/util.rs
\ No newline at end of file
@@ -0,0 +1,18 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::Uint128;
+
+/// Interface a STKN price oracle has to implement
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    Price {}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OraclePriceResponse {
+    /// STKN price in PUSD, with 6 decimals
+    pub price: Uint128,
+    /// Unix time in seconds
+    pub last_updated: u64
+}
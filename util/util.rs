@@ -0,0 +1,199 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::{
+    to_binary, Addr, Decimal, QuerierWrapper, QueryRequest, StdError, StdResult, Uint128, WasmQuery
+};
+use cw20::{Denom, BalanceResponse as CW20BalanceResponse, Cw20QueryMsg};
+
+pub const NORMAL_DECIMAL: u128 = 1000000u128;
+
+/// Manager queries used by the other contracts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ManagerQueryMsg {
+    Config {},
+    CheckStockSubcontract {
+        id: u32,
+        address: Addr
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ManagerConfigResponse {
+    /// None once the ownership was renounced
+    pub owner: Option<Addr>,
+    pub stkn_address: Addr,
+    pub pusd_address: Addr,
+    pub staking_address: Addr,
+
+    pub cw20_code_id: u64,
+    pub stock_code_id: u64,
+    pub pool_code_id: u64,
+
+    pub staking_code_id: u64,
+
+    pub shorting_code_id: u64,
+    pub trading_code_id: u64,
+    pub providing_code_id: u64,
+
+    pub price: Uint128,
+    pub price_updated_at: u64,
+    pub oracle_address: Option<Addr>,
+    pub max_price_age: Option<u64>,
+    pub buy_pusd_fee_bps: u64,
+    pub sell_pusd_fee_bps: u64,
+    pub fee_collector: Addr,
+    pub accrued_fees: Uint128,
+    pub min_stkn_reserve: Uint128,
+    pub min_collateral_ratio: Option<Decimal>,
+    pub stkn_amount: Uint128,
+    pub max_stock_id: u32,
+    pub enabled: bool,
+    pub providing_sync_interval: u64,
+    pub keeper_reward: Uint128
+}
+
+/// Sent by the stock subcontracts to the manager
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GlobalExecuteMsg {
+    MintPusd {
+        id: u32,
+        recipient: Addr,
+        amount: Uint128
+    },
+    MintStock {
+        id: u32,
+        recipient: Addr,
+        amount: Uint128
+    },
+    TransferStkn {
+        id: u32,
+        recipient: Addr,
+        amount: Uint128
+    },
+    /// Sent by a pool after SyncPrice
+    RecordSync {}
+}
+
+/// Cw20 receive hooks the stock subcontracts send to the manager
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GlobalReceiveMsg {
+    /// Burns the PUSD or stock tokens sent and lowers the outstanding mint amount of the stock
+    Burn {
+        id: u32
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StockQueryMsg {
+    Config {}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StockConfigResponse {
+    pub id: u32,
+    pub manager_address: Addr,
+    pub pool_address: Addr,
+    pub shorting_address: Addr,
+    pub trading_address: Addr,
+    pub providing_address: Addr,
+    pub price: Uint128
+}
+
+/// Answered by the shorting and trading contracts of a stock, the manager refuses a split while
+/// they have open positions. The handlers live in those contracts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubcontractQueryMsg {
+    OpenPositions {}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OpenPositionsResponse {
+    pub count: u64
+}
+
+pub fn get_manager_config(
+    querier: QuerierWrapper,
+    manager_address: Addr
+) -> StdResult<ManagerConfigResponse> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: manager_address.into(),
+        msg: to_binary(&ManagerQueryMsg::Config {})?,
+    }))
+}
+
+pub fn get_stock_config(
+    querier: QuerierWrapper,
+    stock_address: Addr
+) -> StdResult<StockConfigResponse> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: stock_address.into(),
+        msg: to_binary(&StockQueryMsg::Config {})?,
+    }))
+}
+
+pub fn check_stock_owner(
+    querier: QuerierWrapper,
+    stock_address: Addr,
+    address: Addr
+) -> StdResult<bool> {
+    let stock_response = get_stock_config(querier, stock_address)?;
+    let manager_response = get_manager_config(querier, stock_response.manager_address)?;
+    if manager_response.owner.as_ref() != Some(&address) {
+        return Err(StdError::generic_err("Unauthorized"));
+    }
+    Ok(true)
+}
+
+pub fn check_stock_enabled(
+    querier: QuerierWrapper,
+    stock_address: Addr
+) -> StdResult<bool> {
+    let stock_response = get_stock_config(querier, stock_address)?;
+    let manager_response = get_manager_config(querier, stock_response.manager_address)?;
+    if !manager_response.enabled {
+        return Err(StdError::generic_err("Disabled"));
+    }
+    Ok(true)
+}
+
+/// The manager decides which addresses act for the stock
+pub fn check_stock_subcontract(
+    querier: QuerierWrapper,
+    stock_address: Addr,
+    address: Addr
+) -> StdResult<bool> {
+    let stock_response = get_stock_config(querier, stock_address)?;
+    let is_subcontract: bool = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: stock_response.manager_address.into(),
+        msg: to_binary(&ManagerQueryMsg::CheckStockSubcontract { id: stock_response.id, address })?,
+    }))?;
+    if !is_subcontract {
+        return Err(StdError::generic_err("Unauthorized"));
+    }
+    Ok(true)
+}
+
+pub fn get_token_amount(
+    querier: QuerierWrapper,
+    denom: Denom,
+    contract_addr: Addr
+) -> StdResult<Uint128> {
+    match denom {
+        Denom::Native(native_str) => {
+            let native_response = querier.query_balance(contract_addr, native_str)?;
+            Ok(native_response.amount)
+        },
+        Denom::Cw20(cw20_address) => {
+            let balance_response: CW20BalanceResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: cw20_address.into(),
+                msg: to_binary(&Cw20QueryMsg::Balance { address: contract_addr.into() })?,
+            }))?;
+            Ok(balance_response.balance)
+        }
+    }
+}